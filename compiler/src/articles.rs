@@ -1,71 +1,403 @@
+use pulldown_cmark::{CodeBlockKind, Event, Tag, TagEnd};
 use std::path::{Path, PathBuf};
 
 use crate::{
-    INPUT_DIR, OUTPUT_DIR,
-    info::{ArticleInfo, InfoWrangler},
+    config::Config,
+    error::{Error, Result},
+    front_matter::FrontMatter,
+    headings::Headings,
+    highlight::Highlighter,
+    ignore::IgnoreRules,
+    info::{ArticleInfo, InfoWrangler, Neighbours, Redirect},
+    math::Math,
+    search::{Bloom, Search},
+    tags::TagPages,
     utils::Utils,
 };
 
+// bump this whenever the article page html changes, so every article is rendered again
+const TEMPLATE_VERSION: u32 = 5;
+
 pub struct Articles {}
 
 impl Articles {
-    pub fn must_get_article_paths() -> Vec<PathBuf> {
-        let read_dir = match std::fs::read_dir(INPUT_DIR) {
-            Ok(rd) => rd,
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                std::fs::create_dir_all(INPUT_DIR).expect("Failed to create output directory");
-                return vec![];
+    // every article in the input directory and its subfolders, sorted
+    // hidden files and folders, files without an article extension, and ignored paths are skipped
+    // only build and new create it, so nothing else writes when it's missing (like check)
+    pub fn create_input_dir(config: &Config) -> Result<()> {
+        std::fs::create_dir_all(&config.input_dir)
+            .map_err(|e| Error::io("create input directory", &config.input_dir, e))
+    }
+
+    // a missing input directory is an error, see create_input_dir
+    pub fn get_article_paths(config: &Config) -> Result<Vec<PathBuf>> {
+        let input_dir = &config.input_dir;
+        if let Err(e) = std::fs::metadata(input_dir) {
+            return Err(Error::io("read input directory", input_dir, e));
+        }
+
+        let ignore_rules = IgnoreRules::load(input_dir, &config.ignore_file)?;
+        let mut paths = Vec::new();
+        Self::collect_article_paths(config, &ignore_rules, input_dir, &mut paths)?;
+        paths.sort();
+
+        Ok(paths)
+    }
+
+    fn collect_article_paths(
+        config: &Config,
+        ignore_rules: &IgnoreRules,
+        dir: &Path,
+        paths: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        let read_dir =
+            std::fs::read_dir(dir).map_err(|e| Error::io("read input directory", dir, e))?;
+
+        for entry in read_dir {
+            let entry = entry.map_err(|e| Error::io("read input directory", dir, e))?;
+            let path = entry.path();
+
+            let name = entry.file_name();
+            if name.to_string_lossy().starts_with('.') {
+                continue;
             }
-            Err(e) => {
-                panic!("Failed to read input directory ({}): {}", INPUT_DIR, e);
+
+            let file_type = entry
+                .file_type()
+                .map_err(|e| Error::io("read file type", &path, e))?;
+            let relative_path = Self::relative_url_path(config, &path);
+            if ignore_rules.is_ignored(&relative_path, file_type.is_dir()) {
+                continue;
             }
-        };
 
-        let mut paths = Vec::new();
-        for file in read_dir {
-            let path = file.expect("Failed to read file").path();
-            paths.push(path);
+            if file_type.is_dir() {
+                Self::collect_article_paths(config, ignore_rules, &path, paths)?;
+            } else if Self::has_article_extension(config, &path) {
+                paths.push(path);
+            }
         }
 
-        paths
+        Ok(())
     }
 
-    pub fn process(path: &Path, info_wrangler: &mut InfoWrangler) {
-        let content = match std::fs::read_to_string(path) {
-            Ok(c) => c,
-            Err(e) => {
-                panic!("Failed to read file {:?}: {}", path, e);
-            }
+    fn has_article_extension(config: &Config, path: &Path) -> bool {
+        let extension = match path.extension().and_then(|e| e.to_str()) {
+            Some(e) => e.to_lowercase(),
+            None => return false,
         };
+        config
+            .extensions
+            .iter()
+            .any(|allowed| allowed.trim_start_matches('.').to_lowercase() == extension)
+    }
+
+    // path inside the input directory, always with / separators
+    fn relative_url_path(config: &Config, path: &Path) -> String {
+        let relative_path = path.strip_prefix(&config.input_dir).unwrap_or(path);
+        relative_path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    pub fn read_front_matter(path: &Path) -> Result<FrontMatter> {
+        let content = Self::read_source(path)?;
+        let (front_matter, _) = Self::parse_front_matter(path, &content)?;
+        Ok(front_matter)
+    }
+
+    fn read_source(path: &Path) -> Result<String> {
+        std::fs::read_to_string(path).map_err(|e| Error::io("read article", path, e))
+    }
 
+    fn parse_front_matter<'a>(path: &Path, content: &'a str) -> Result<(FrontMatter, &'a str)> {
+        FrontMatter::parse(content).map_err(|message| Error::FrontMatter {
+            path: path.to_path_buf(),
+            message,
+        })
+    }
+
+    // renders the article unless nothing it depends on has changed since the last build
+    // returns whether the html file was written
+    pub fn process(
+        config: &Config,
+        path: &Path,
+        info_wrangler: &mut InfoWrangler,
+        force: bool,
+    ) -> Result<bool> {
+        let content = Self::read_source(path)?;
         info_wrangler.update_content(path, &content);
+        let article_info = Self::get_article(path, info_wrangler)?;
+        let file_path = config
+            .output_dir
+            .join(article_info.safe_filename.clone() + ".html");
+
+        if article_info.draft {
+            // unpublish anything left over from before it was a draft
+            return match std::fs::remove_file(&file_path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    Err(Error::io("unpublish draft", &file_path, e))
+                }
+                _ => Ok(false),
+            };
+        }
+
+        // the search's body filter only changes with the content, not with the rest of the page
+        let is_current = |bloom: &Bloom| Search::is_current(&config.search, bloom);
+        if !article_info.body_filter.as_ref().is_some_and(is_current) {
+            let text = Self::plain_text(config, path, &content)?;
+            info_wrangler.set_body_filter(path, Search::body_bloom(&config.search, &text));
+        }
+        let article_info = Self::get_article(path, info_wrangler)?;
+
+        let neighbours = info_wrangler.get_neighbours(path);
+        let render_hash = Self::render_hash(config, article_info, &neighbours);
+        if !force && article_info.render_hash == render_hash && file_path.exists() {
+            return Ok(false);
+        }
+
+        let (html_content, head_style) = Self::render_body(config, path, &content)?;
+        let full_html = Self::compile_full_html(
+            config,
+            article_info,
+            &neighbours,
+            &html_content,
+            &head_style,
+        );
+        Utils::write_file(&file_path, full_html)?;
+        info_wrangler.set_render_hash(path, render_hash);
+        // so the feeds don't render it again
+        if config.feed.full_content {
+            info_wrangler.set_body_html(path, html_content);
+        }
+
+        Ok(true)
+    }
+
+    // returns the article's safe filename and its full html, without writing anything
+    pub fn render(
+        config: &Config,
+        path: &Path,
+        info_wrangler: &mut InfoWrangler,
+    ) -> Result<(String, String)> {
+        let content = Self::read_source(path)?;
+        info_wrangler.update_content(path, &content);
+        let article_info = Self::get_article(path, info_wrangler)?;
+        let neighbours = info_wrangler.get_neighbours(path);
+        let full_html = Self::render_content(config, path, &content, article_info, &neighbours)?;
+
+        Ok((article_info.safe_filename.clone(), full_html))
+    }
+
+    // whether the article's page is out of date, judging by the info file alone
+    // an article whose source changed is out of date too, but this doesn't read the source
+    pub fn needs_render(config: &Config, path: &Path, info_wrangler: &InfoWrangler) -> bool {
+        let Some(article_info) = info_wrangler.get_article(path) else {
+            return true;
+        };
+        if article_info.draft {
+            return false;
+        }
+
+        let neighbours = info_wrangler.get_neighbours(path);
+        let file_path = config
+            .output_dir
+            .join(article_info.safe_filename.clone() + ".html");
+        article_info.render_hash != Self::render_hash(config, article_info, &neighbours)
+            || !file_path.exists()
+    }
+
+    fn get_article<'a>(path: &Path, info_wrangler: &'a InfoWrangler) -> Result<&'a ArticleInfo> {
+        info_wrangler
+            .get_article(path)
+            .ok_or_else(|| Error::MissingArticleInfo {
+                path: path.to_path_buf(),
+            })
+    }
+
+    fn render_content(
+        config: &Config,
+        path: &Path,
+        content: &str,
+        article_info: &ArticleInfo,
+        neighbours: &Neighbours,
+    ) -> Result<String> {
+        let (html_content, head_style) = Self::render_body(config, path, content)?;
+
+        Ok(Self::compile_full_html(
+            config,
+            article_info,
+            neighbours,
+            &html_content,
+            &head_style,
+        ))
+    }
+
+    // just the article's own html, like for a feed, without the rest of the page around it
+    pub fn render_body_html(config: &Config, path: &Path) -> Result<String> {
+        let content = Self::read_source(path)?;
+        let (html_content, _) = Self::render_body(config, path, &content)?;
+        Ok(html_content)
+    }
+
+    // returns the markdown as html, and any css it needs
+    fn render_body(config: &Config, path: &Path, content: &str) -> Result<(String, String)> {
+        let (front_matter, body) = Self::parse_front_matter(path, content)?;
+        let options = config.markdown.with_overrides(&front_matter.markdown);
+
+        let parser = pulldown_cmark::Parser::new_ext(body, options.parser_options());
+        let mut events: Vec<Event> = parser
+            .map(|event| match event {
+                Event::InlineMath(latex) => {
+                    Event::InlineHtml(Math::to_mathml(&latex, false).into())
+                }
+                Event::DisplayMath(latex) => {
+                    Event::InlineHtml(Math::to_mathml(&latex, true).into())
+                }
+                event => event,
+            })
+            .collect();
+
+        Headings::anchor(&mut events, front_matter.toc);
+
+        let mut head_style = String::new();
+        if config.highlight.enabled && Self::highlight_code_blocks(&mut events) {
+            head_style.push_str(&Highlighter::css(&config.highlight));
+        }
 
         let mut html_content = String::new();
-        let parser = pulldown_cmark::Parser::new(&content);
-        pulldown_cmark::html::push_html(&mut html_content, parser);
+        pulldown_cmark::html::push_html(&mut html_content, events.into_iter());
+
+        Ok((html_content, head_style))
+    }
 
-        let article_info = match info_wrangler.get_article(path) {
-            Some(info) => info,
-            None => {
-                panic!("Article info not found for path {:?}", path);
+    // plain_text, straight from the source file
+    pub fn read_plain_text(config: &Config, path: &Path) -> Result<String> {
+        let content = Self::read_source(path)?;
+        Self::plain_text(config, path, &content)
+    }
+
+    // the words of the article, as a reader sees them, for the search
+    // code and math are kept as written, markup and raw html are left out
+    pub fn plain_text(config: &Config, path: &Path, content: &str) -> Result<String> {
+        let (front_matter, body) = Self::parse_front_matter(path, content)?;
+        let options = config.markdown.with_overrides(&front_matter.markdown);
+
+        let mut text = String::new();
+        for event in pulldown_cmark::Parser::new_ext(body, options.parser_options()) {
+            match event {
+                Event::Text(t) | Event::Code(t) | Event::InlineMath(t) | Event::DisplayMath(t) => {
+                    text.push_str(&t);
+                    text.push(' ');
+                }
+                Event::SoftBreak | Event::HardBreak => text.push(' '),
+                _ => {}
             }
+        }
+        Ok(text)
+    }
+
+    // replaces every code block with its highlighted html, returns whether there were any
+    fn highlight_code_blocks(events: &mut Vec<Event>) -> bool {
+        let mut highlighted = Vec::with_capacity(events.len());
+        let mut code_block: Option<(Option<String>, String)> = None;
+        let mut found = false;
+
+        for event in events.drain(..) {
+            match (&mut code_block, event) {
+                (None, Event::Start(Tag::CodeBlock(kind))) => {
+                    let language = match kind {
+                        CodeBlockKind::Fenced(info) => {
+                            info.split_whitespace().next().map(|lang| lang.to_string())
+                        }
+                        CodeBlockKind::Indented => None,
+                    };
+                    code_block = Some((language, String::new()));
+                }
+                (Some((_, code)), Event::Text(text)) => code.push_str(&text),
+                (Some(_), Event::End(TagEnd::CodeBlock)) => {
+                    if let Some((language, code)) = code_block.take() {
+                        let html = Highlighter::highlight(&code, language.as_deref());
+                        highlighted.push(Event::Html(html.into()));
+                        found = true;
+                    }
+                }
+                (_, event) => highlighted.push(event),
+            }
+        }
+
+        *events = highlighted;
+        found
+    }
+
+    // if this hasn't changed since the last render, the html on disk is still correct
+    fn render_hash(config: &Config, article_info: &ArticleInfo, neighbours: &Neighbours) -> String {
+        let neighbour = |article: Option<&ArticleInfo>| match article {
+            Some(a) => format!("{}\u{1f}{}", a.safe_filename, a.title()),
+            None => "".to_string(),
         };
-        let full_html = Self::compile_full_html(article_info, &html_content);
+        let inputs = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+            TEMPLATE_VERSION,
+            config.render_fingerprint(),
+            article_info.content_hash,
+            article_info.title(),
+            article_info.tags.join("\u{1f}"),
+            article_info.created_at,
+            article_info.updated_at,
+            neighbour(neighbours.older),
+            neighbour(neighbours.newer),
+        );
+        Utils::hash_base64(inputs.as_bytes())
+    }
+
+    // old urls of renamed articles keep working by bouncing to the new url
+    pub fn save_redirect(config: &Config, redirect: &Redirect) -> Result<()> {
+        let target = Utils::escape_html(&format!("{}/{}.html", config.base_path, redirect.to));
+        let html = format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta charset="UTF-8">
+        <title>Moved</title>
+        <link rel="canonical" href="{}">
+        <meta http-equiv="refresh" content="0; url={}">
+    </head>
+    <body>
+        <p>This article has moved to <a href="{}">{}</a>.</p>
+    </body>
+</html>
+"#,
+            target, target, target, target
+        );
 
-        let file_path = Path::new(OUTPUT_DIR).join(article_info.safe_filename.clone() + ".html");
-        std::fs::create_dir_all(file_path.parent().unwrap())
-            .expect("Failed to create output directory");
-        std::fs::write(file_path, full_html).expect("Failed to write output file");
+        let file_path = config.output_dir.join(redirect.from.clone() + ".html");
+        Utils::write_file(&file_path, html)
     }
 
-    fn compile_full_html(article_info: &ArticleInfo, html_content: &str) -> String {
+    // head_style is extra css for this page only, like the highlighting theme
+    fn compile_full_html(
+        config: &Config,
+        article_info: &ArticleInfo,
+        neighbours: &Neighbours,
+        html_content: &str,
+        head_style: &str,
+    ) -> String {
         let tags = if article_info.tags.is_empty() {
             "".to_string()
         } else {
             let tag_spans: Vec<String> = article_info
                 .tags
                 .iter()
-                .map(|tag| format!(r#"<span class="tag">{}</span>"#, tag))
+                .map(|tag| match TagPages::tag_url(config, tag) {
+                    Some(url) => format!(
+                        r#"<a class="tag" href="{}">{}</a>"#,
+                        Utils::escape_html(&url),
+                        Utils::escape_html(tag)
+                    ),
+                    None => format!(r#"<span class="tag">{}</span>"#, Utils::escape_html(tag)),
+                })
                 .collect();
             format!(
                 r#"<p class="tags"><i>tags</i>: {}</p>"#,
@@ -82,7 +414,27 @@ impl Articles {
             last_updated
         );
 
-        // TODO: button for previous / next article?
+        let neighbour_link = |article: Option<&ArticleInfo>, class: &str, label: &str| match article
+        {
+            Some(a) => format!(
+                r#"<a class="{}" href="{}/{}.html"><i>{}</i>: {}</a>"#,
+                class,
+                Utils::escape_html(&config.base_path),
+                Utils::escape_html(&a.safe_filename),
+                label,
+                Utils::escape_html(a.title())
+            ),
+            None => "<span></span>".to_string(),
+        };
+        let navigation = if neighbours.older.is_none() && neighbours.newer.is_none() {
+            "".to_string()
+        } else {
+            format!(
+                r#"<nav class="article-nav">{}{}</nav>"#,
+                neighbour_link(neighbours.older, "previous", "previous"),
+                neighbour_link(neighbours.newer, "next", "next")
+            )
+        };
 
         format!(
             r#"<!DOCTYPE html>
@@ -101,6 +453,29 @@ impl Articles {
                 text-align: right;
                 padding: 8px; /* Adds spacing between columns */
             }}
+            pre {{
+                padding: 8px;
+                overflow-x: auto;
+            }}
+            .anchor {{
+                margin-left: 0.3em;
+                text-decoration: none;
+                opacity: 0;
+            }}
+            .article-nav {{
+                display: flex;
+                justify-content: space-between;
+                gap: 1em;
+                margin-top: 2em;
+            }}
+            .article-nav .next {{
+                text-align: right;
+            }}
+            h1:hover .anchor, h2:hover .anchor, h3:hover .anchor,
+            h4:hover .anchor, h5:hover .anchor, h6:hover .anchor, .anchor:focus {{
+                opacity: 1;
+            }}
+{}
         </style>
     </head>
     <body>
@@ -108,11 +483,207 @@ impl Articles {
             {}
             {}
             {}
+            {}
         </div>
     </body>
 </html>
 "#,
-            article_info.original_filename, html_content, tags, last_updated,
+            Utils::escape_html(article_info.title()),
+            head_style,
+            html_content,
+            tags,
+            last_updated,
+            navigation,
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::MarkdownOptions;
+
+    fn render(markdown: MarkdownOptions, content: &str) -> String {
+        let config = Config {
+            markdown,
+            ..Config::default()
+        };
+        let (html, _) = Articles::render_body(&config, Path::new("test.md"), content).unwrap();
+        html
+    }
+
+    // (on, off) renders of the content, off only turns off the one extension
+    fn render_both(content: &str, off: MarkdownOptions) -> (String, String) {
+        (
+            render(MarkdownOptions::default(), content),
+            render(off, content),
+        )
+    }
+
+    #[test]
+    fn tables() {
+        let content = "| a | b |\n|---|---|\n| 1 | 2 |\n";
+        let (on, off) = render_both(
+            content,
+            MarkdownOptions {
+                tables: false,
+                ..MarkdownOptions::default()
+            },
+        );
+        assert!(on.contains("<table>"), "{}", on);
+        assert!(on.contains("<td>1</td>"), "{}", on);
+        assert!(!off.contains("<table>"), "{}", off);
+    }
+
+    #[test]
+    fn footnotes() {
+        let content = "text[^1]\n\n[^1]: the note\n";
+        let (on, off) = render_both(
+            content,
+            MarkdownOptions {
+                footnotes: false,
+                ..MarkdownOptions::default()
+            },
+        );
+        assert!(on.contains(r#"class="footnote-reference""#), "{}", on);
+        assert!(on.contains(r#"class="footnote-definition""#), "{}", on);
+        assert!(!off.contains("footnote"), "{}", off);
+    }
+
+    #[test]
+    fn strikethrough() {
+        let (on, off) = render_both(
+            "~~x~~\n",
+            MarkdownOptions {
+                strikethrough: false,
+                ..MarkdownOptions::default()
+            },
+        );
+        assert!(on.contains("<del>x</del>"), "{}", on);
+        assert!(off.contains("~~x~~"), "{}", off);
+    }
+
+    #[test]
+    fn tasklists() {
+        let (on, off) = render_both(
+            "- [x] done\n",
+            MarkdownOptions {
+                tasklists: false,
+                ..MarkdownOptions::default()
+            },
+        );
+        assert!(on.contains(r#"type="checkbox""#), "{}", on);
+        assert!(on.contains("checked"), "{}", on);
+        assert!(!off.contains("checkbox"), "{}", off);
+        assert!(off.contains("[x] done"), "{}", off);
+    }
+
+    #[test]
+    fn heading_attributes() {
+        let (on, off) = render_both(
+            "## Title {#custom}\n",
+            MarkdownOptions {
+                heading_attributes: false,
+                ..MarkdownOptions::default()
+            },
+        );
+        assert!(on.contains(r#"<h2 id="custom">"#), "{}", on);
+        assert!(!off.contains(r#"id="custom""#), "{}", off);
+        assert!(off.contains("{#custom}"), "{}", off);
+    }
+
+    #[test]
+    fn smart_punctuation() {
+        let (on, off) = render_both(
+            "\"quoted\" -- it's...\n",
+            MarkdownOptions {
+                smart_punctuation: false,
+                ..MarkdownOptions::default()
+            },
+        );
+        assert!(on.contains("\u{201c}quoted\u{201d}"), "{}", on);
+        assert!(on.contains("\u{2013}"), "{}", on);
+        assert!(on.contains("it\u{2019}s\u{2026}"), "{}", on);
+        assert!(off.contains("\"quoted\" -- it's..."), "{}", off);
+    }
+
+    #[test]
+    fn math() {
+        let (on, off) = render_both(
+            "$x^2$\n",
+            MarkdownOptions {
+                math: false,
+                ..MarkdownOptions::default()
+            },
+        );
+        assert!(on.contains("<math"), "{}", on);
+        assert!(!off.contains("<math"), "{}", off);
+        assert!(off.contains("$x^2$"), "{}", off);
+    }
+
+    #[test]
+    fn front_matter_overrides_the_site() {
+        let site = MarkdownOptions {
+            tables: false,
+            ..MarkdownOptions::default()
+        };
+
+        let content = "---\nmarkdown: { tables: true, smart_punctuation: false }\n---\n\
+            \"quoted\"\n\n| a | b |\n|---|---|\n| 1 | 2 |\n";
+        let html = render(site.clone(), content);
+        assert!(html.contains("<table>"), "{}", html);
+        assert!(html.contains("\"quoted\""), "{}", html);
+
+        // without the override, the site's settings apply
+        let content = "\"quoted\"\n\n| a | b |\n|---|---|\n| 1 | 2 |\n";
+        let html = render(site, content);
+        assert!(!html.contains("<table>"), "{}", html);
+        assert!(html.contains("\u{201c}quoted\u{201d}"), "{}", html);
+    }
+
+    fn render_default(content: &str) -> String {
+        render(MarkdownOptions::default(), content)
+    }
+
+    #[test]
+    fn repeated_headings_get_unique_ids() {
+        let html = render_default("## Foo\n\n## Foo\n\n### Foo\n");
+        assert!(html.contains(r#"<h2 id="foo">"#));
+        assert!(html.contains(r#"<h2 id="foo-1">"#));
+        assert!(html.contains(r#"<h3 id="foo-2">"#));
+        assert!(html.contains(r##"href="#foo-1""##));
+    }
+
+    #[test]
+    fn set_ids_are_kept_and_reserved() {
+        // {#foo-1} is taken before the headings are numbered, so the repeat skips it
+        let html = render_default("## Foo\n\n## Foo\n\n## Other {#foo-1}\n");
+        assert!(html.contains(r#"<h2 id="foo">"#));
+        assert!(html.contains(r#"<h2 id="foo-2">"#));
+        assert!(html.contains(r#"<h2 id="foo-1">Other"#));
+    }
+
+    #[test]
+    fn toc_nests_skipped_levels() {
+        let html =
+            render_default("---\ntoc: true\n---\n# Title\n\n## A\n\n#### Deep\n\n## B\n\n### C\n");
+        // after the title, which isn't in it, with an empty level between A and Deep
+        assert!(html.contains(concat!(
+            r#"</h1>"#,
+            "\n",
+            r##"<nav class="toc"><ul><li><a href="#a">A</a><ul><li><ul><li><a href="#deep">Deep</a></li></ul></li></ul></li>"##,
+            r##"<li><a href="#b">B</a><ul><li><a href="#c">C</a></li></ul></li></ul></nav>"##
+        )));
+    }
+
+    #[test]
+    fn toc_marker_starting_deeper() {
+        let html = render_default("<!-- toc -->\n\n### Three\n\n## Two\n");
+        assert!(html.starts_with(concat!(
+            r##"<nav class="toc"><ul><li><ul><li><a href="#three">Three</a></li></ul></li>"##,
+            r##"<li><a href="#two">Two</a></li></ul></nav>"##
+        )));
+        // no toc without the marker or toc: true
+        assert!(!render_default("## Two\n").contains("toc"));
+    }
+}
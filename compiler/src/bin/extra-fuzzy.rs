@@ -0,0 +1,258 @@
+// measures how well the index page's search ranks articles, without a browser
+// a port of support/extra-fuzzy/main.go that uses the compiler's own search.rs:
+// articles are chunks of the gutenberg texts next to main.go, search terms are random slices of them,
+// and the ranking the bloom filters give is compared to the exact one (counting shared trigrams)
+// by kendall tau distance, for a range of false positive rates and hash counts
+//
+// cargo run --release --bin extra-fuzzy -- [--corpus-dir ../support/extra-fuzzy] [--output results.md]
+
+use blag_compiler::cli::Cli;
+use blag_compiler::search::{Search, SearchOptions};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const FILE_NAMES: [&str; 4] = [
+    "little-women.txt",
+    "also-rises.txt",
+    "huck-finn.txt",
+    "frankenstein.txt",
+];
+const ARTICLES_PER_FILE: usize = 100;
+const ARTICLE_LENGTH: usize = 1600; // characters of source text in each article
+const TERMS_PER_CELL: usize = 50; // search terms averaged over, per table cell
+const TOP_N: usize = 10; // only the top of the exact ranking is compared, like the first page of results
+const SEED: u64 = 0x5eed_b10f; // fixed, so runs before and after a change are comparable
+
+const MAX_HASHES: [u32; 3] = [1, 2, 3];
+const FALSE_POSITIVE_RATES: [f64; 5] = [0.2, 0.1, 0.05, 0.02, 0.01];
+const TERM_LENGTHS: [usize; 10] = [3, 4, 5, 6, 7, 8, 9, 10, 16, 32];
+
+struct Article {
+    squashed: String,
+    trigrams: BTreeSet<String>,
+}
+
+// a filter as the page sees it, after decoding
+struct Filter {
+    bits: u64,
+    hashes: u32,
+    bytes: Vec<u8>,
+}
+
+// (max_hashes, false positive rate index, term length) -> average distance of each file
+type Results = HashMap<(u32, usize, usize), Vec<f64>>;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let corpus_dir = Cli::flag_value(&args, &["--corpus-dir"])
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("../support/extra-fuzzy"));
+    let output = Cli::flag_value(&args, &["--output"])
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("results.md"));
+
+    match run(&corpus_dir, &output) {
+        Ok(()) => {
+            println!("results written to {}", output.display());
+            ExitCode::SUCCESS
+        }
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(corpus_dir: &Path, output: &Path) -> Result<(), String> {
+    let mut rng = Rng(SEED);
+    let mut results: Results = HashMap::new();
+
+    for file_name in FILE_NAMES {
+        println!("processing {}", file_name);
+        let path = corpus_dir.join(file_name);
+        let source = std::fs::read_to_string(&path)
+            .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        let articles =
+            create_articles(&source).ok_or_else(|| format!("{} is too short", path.display()))?;
+
+        for max_hashes in MAX_HASHES {
+            for (rate_index, false_positive_rate) in FALSE_POSITIVE_RATES.iter().enumerate() {
+                let options = SearchOptions {
+                    false_positive_rate: *false_positive_rate,
+                    max_hashes,
+                    ..SearchOptions::default()
+                };
+                let filters: Vec<Filter> = articles
+                    .iter()
+                    .map(|article| build_filter(&options, &article.trigrams))
+                    .collect();
+
+                for term_length in TERM_LENGTHS {
+                    let mut total = 0;
+                    for _ in 0..TERMS_PER_CELL {
+                        let term = pick_search_term(&mut rng, &articles, term_length);
+                        let exact = rank(articles.iter().map(|a| exact_score(&term, a)));
+                        let hashes: Vec<u64> = term.iter().map(|t| Search::djb2tri(t)).collect();
+                        let bloom = rank(filters.iter().map(|f| bloom_score(&hashes, f)));
+                        total += kendall_tau_distance(TOP_N, &exact, &bloom);
+                    }
+                    results
+                        .entry((max_hashes, rate_index, term_length))
+                        .or_default()
+                        .push(total as f64 / TERMS_PER_CELL as f64);
+                }
+            }
+        }
+    }
+
+    std::fs::write(output, format_results(&results))
+        .map_err(|e| format!("couldn't write {}: {}", output.display(), e))
+}
+
+// consecutive chunks of the source text, squashed the same way the page squashes article text
+fn create_articles(source: &str) -> Option<Vec<Article>> {
+    let chars: Vec<char> = source.chars().collect();
+    if chars.len() < ARTICLES_PER_FILE * ARTICLE_LENGTH {
+        return None;
+    }
+
+    let articles = chars
+        .chunks(ARTICLE_LENGTH)
+        .take(ARTICLES_PER_FILE)
+        .map(|chunk| {
+            let squashed = Search::squash_text(&chunk.iter().collect::<String>());
+            let trigrams = Search::build_trigrams(&squashed);
+            Article { squashed, trigrams }
+        })
+        .collect();
+    Some(articles)
+}
+
+// sized and filled by search.rs, exactly as the compiler does it for an article's body
+fn build_filter(options: &SearchOptions, trigrams: &BTreeSet<String>) -> Filter {
+    let (bits, hashes) = Search::size(options, trigrams.len());
+    Filter {
+        bits,
+        hashes,
+        bytes: Search::blossom(trigrams, bits, hashes),
+    }
+}
+
+// a random slice of a random article, as trigrams
+fn pick_search_term(rng: &mut Rng, articles: &[Article], term_length: usize) -> BTreeSet<String> {
+    let article = &articles[rng.below(articles.len())];
+    let start = rng.below(article.squashed.len().saturating_sub(term_length).max(1));
+    let end = (start + term_length).min(article.squashed.len());
+    // squashed text is all ascii, so any byte index is a character boundary
+    Search::build_trigrams(&article.squashed[start..end])
+}
+
+// how many of the term's trigrams the article really has
+fn exact_score(term: &BTreeSet<String>, article: &Article) -> usize {
+    term.iter()
+        .filter(|trigram| article.trigrams.contains(*trigram))
+        .count()
+}
+
+// how many of the term's trigrams the filter says the article has, like countMatches in html.rs
+fn bloom_score(term_hashes: &[u64], filter: &Filter) -> usize {
+    term_hashes
+        .iter()
+        .filter(|hash| {
+            Search::bit_positions(**hash, filter.bits, filter.hashes)
+                .all(|bit_pos| filter.bytes[(bit_pos / 8) as usize] & (1 << (bit_pos % 8)) != 0)
+        })
+        .count()
+}
+
+// article indexes, highest score first, ties stay in article order (like the page's stable sort)
+fn rank(scores: impl Iterator<Item = usize>) -> Vec<usize> {
+    let scores: Vec<usize> = scores.collect();
+    let mut ranking: Vec<usize> = (0..scores.len()).collect();
+    ranking.sort_by(|a, b| scores[*b].cmp(&scores[*a]));
+    ranking
+}
+
+// how many pairs in the top n of the exact ranking the bloom ranking puts the other way round,
+// out of 1000: 0 is perfect agreement, 1000 is complete disagreement
+fn kendall_tau_distance(top_n: usize, exact: &[usize], bloom: &[usize]) -> usize {
+    let exact = &exact[..exact.len().min(top_n)];
+    let mut bloom_rank = vec![0; bloom.len()];
+    for (rank, index) in bloom.iter().enumerate() {
+        bloom_rank[*index] = rank;
+    }
+
+    let mut distance = 0;
+    for x in 0..exact.len() {
+        for y in x + 1..exact.len() {
+            if bloom_rank[exact[x]] > bloom_rank[exact[y]] {
+                distance += 1;
+            }
+        }
+    }
+
+    let pairs = exact.len() * exact.len().saturating_sub(1) / 2;
+    if pairs == 0 {
+        return 0;
+    }
+    (distance as f64 / pairs as f64 * 1000.0).round() as usize
+}
+
+// one table per max_hashes, a row per false positive rate and a column per term length,
+// every cell averaged over the files
+fn format_results(results: &Results) -> String {
+    let mut md = String::new();
+    for max_hashes in MAX_HASHES {
+        let _ = writeln!(md, "## max_hashes = {}\n", max_hashes);
+
+        md.push_str("| false positive rate ");
+        for term_length in TERM_LENGTHS {
+            let _ = write!(md, "| {} ", term_length);
+        }
+        md.push_str("|\n|---------------------");
+        md.push_str(&"|------".repeat(TERM_LENGTHS.len()));
+        md.push_str("|\n");
+
+        let mut cells = Vec::new();
+        for (rate_index, false_positive_rate) in FALSE_POSITIVE_RATES.iter().enumerate() {
+            let _ = write!(md, "| {} ", false_positive_rate);
+            for term_length in TERM_LENGTHS {
+                let per_file = results
+                    .get(&(max_hashes, rate_index, term_length))
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                let average = per_file.iter().sum::<f64>() / per_file.len().max(1) as f64;
+                cells.push(average);
+                let _ = write!(md, "| {} ", average.round());
+            }
+            md.push_str("|\n");
+        }
+
+        let overall = cells.iter().sum::<f64>() / cells.len().max(1) as f64;
+        let _ = writeln!(
+            md,
+            "\nAverage distance for max_hashes={}: {:.2}\n\n---\n",
+            max_hashes, overall
+        );
+    }
+    md
+}
+
+// xorshift64, plenty for picking search terms
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // 0..n
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
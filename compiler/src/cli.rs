@@ -0,0 +1,392 @@
+pub const DEFAULT_PORT: u16 = 8000;
+pub const DEFAULT_SUGGESTION_COUNT: usize = 5;
+
+pub enum Command {
+    Build {
+        upsert_only: bool,
+        prune: bool,
+        dry_run: bool,
+        force: bool,
+    },
+    New {
+        title: String,
+    },
+    Check,
+    Clean,
+    Watch,
+    SuggestTags {
+        count: usize,
+        write: bool,
+    },
+    Serve {
+        port: u16,
+    },
+}
+
+pub enum Parsed {
+    Run(Command),
+    Help(&'static str),
+}
+
+// flags shared by every command, each takes a value
+// these are read by Config::load, the parser only needs to skip over them
+const CONFIG_FLAGS: [&str; 9] = [
+    "--config",
+    "-c",
+    "--info-file",
+    "--input-dir",
+    "--output-dir",
+    "--title",
+    "--base-path",
+    "--source-url",
+    "--site-url",
+];
+
+const MAIN_HELP: &str = "\
+Static site generator for the blag
+
+Usage: blag-compiler [COMMAND] [OPTIONS]
+
+Commands:
+  build         Compile articles into html (default if no command is given)
+  new <TITLE>   Create a new article file and start tracking it
+  check         Validate articles and tracking info without writing anything
+  clean         Delete everything in the output directory
+  watch         Build, then rebuild whenever an article changes
+  suggest-tags  Suggest tags for each article from its text (TF-IDF)
+  serve         Serve the site on localhost, rebuilding and reloading on changes
+  help          Print this message, or the help of a command
+
+Options (all commands):
+  -c, --config <PATH>      Config file to load [default: blag.toml]
+      --info-file <PATH>   Override info_file from the config
+      --input-dir <PATH>   Override input_dir from the config
+      --output-dir <PATH>  Override output_dir from the config
+      --title <TEXT>       Override title from the config
+      --base-path <PATH>   Override base_path from the config
+      --source-url <URL>   Override source_url from the config (empty to hide)
+      --site-url <URL>     Override site_url from the config (empty for no feeds)
+  -h, --help               Print help
+";
+
+const BUILD_HELP: &str = "\
+Compile articles into html
+
+Usage: blag-compiler build [OPTIONS]
+
+Options:
+  -u, --upsert-only  Only update the tracking info file, don't render anything
+      --prune        Forget articles whose source file is gone, and delete their html
+      --dry-run      With --prune, only list what would be removed
+  -f, --force        Render every article, even the ones that haven't changed
+  -h, --help         Print help
+";
+
+const NEW_HELP: &str = "\
+Create a new article file and start tracking it
+
+Usage: blag-compiler new <TITLE> [OPTIONS]
+
+The article is written to <input_dir>/<TITLE>.md and added to the tracking
+info file with today's date. Fails if the file, or an article with the same url,
+already exists.
+
+Options:
+  -h, --help  Print help
+";
+
+const CHECK_HELP: &str = "\
+Validate articles and tracking info without writing anything
+
+Usage: blag-compiler check [OPTIONS]
+
+Exits with a non-zero code if any problems are found.
+
+Options:
+  -h, --help  Print help
+";
+
+const CLEAN_HELP: &str = "\
+Delete everything in the output directory
+
+Usage: blag-compiler clean [OPTIONS]
+
+Options:
+  -h, --help  Print help
+";
+
+const WATCH_HELP: &str = "\
+Build, then rebuild whenever an article changes
+
+Usage: blag-compiler watch [OPTIONS]
+
+Watches the input directory and the tracking info file. Changes are debounced,
+and only the articles that changed are rendered again (plus the index page),
+unless the info file or something else changed, then every article is checked.
+Changes to the config file need a restart.
+
+Options:
+  -h, --help  Print help
+";
+
+const SUGGEST_TAGS_HELP: &str = "\
+Suggest tags for each article from its text (TF-IDF)
+
+Usage: blag-compiler suggest-tags [OPTIONS]
+
+Words that come up often in an article, but rarely in the others, are
+suggested as its tags. Stopwords and tags the article already has are left out.
+Suggestions are only printed, unless --write is given.
+
+Options:
+  -n, --count <N>  Suggestions per article [default: 5]
+      --write      Add the suggestions to the tags in the tracking info file
+                   (articles with tags in their front matter are only printed)
+  -h, --help       Print help
+";
+
+const SERVE_HELP: &str = "\
+Serve the output directory on localhost, rebuilding and reloading on changes
+
+Usage: blag-compiler serve [OPTIONS]
+
+The site is built first, then served under the configured base path, just like
+when it's deployed. Whenever an article or the tracking info file changes, the
+site is rebuilt (incrementally) and open pages reload themselves.
+
+Options:
+  -p, --port <PORT>  Port to listen on [default: 8000]
+  -h, --help         Print help
+";
+
+pub struct Cli {}
+
+impl Cli {
+    // args should not include the program name
+    pub fn parse(args: &[String]) -> Result<Parsed, String> {
+        let (positional, flags) = Self::split_args(args)?;
+
+        let (name, rest) = match positional.split_first() {
+            Some((name, rest)) => (name.as_str(), rest),
+            None => ("build", &[][..]),
+        };
+
+        let wants_help = args.iter().any(|a| a == "--help" || a == "-h");
+
+        match name {
+            "help" => match rest.first() {
+                Some(cmd) => Self::help_for(cmd)
+                    .map(Parsed::Help)
+                    .ok_or(format!("Unknown command '{}'", cmd)),
+                None => Ok(Parsed::Help(MAIN_HELP)),
+            },
+            _ if wants_help => {
+                // "blag-compiler --help" has an implicit build command, but the
+                // user almost certainly wants the overview
+                let help = if positional.is_empty() {
+                    Some(MAIN_HELP)
+                } else {
+                    Self::help_for(name)
+                };
+                help.map(Parsed::Help)
+                    .ok_or(format!("Unknown command '{}'", name))
+            }
+            _ if Self::help_for(name).is_none() => Err(format!("Unknown command '{}'", name)),
+            _ => {
+                Self::expect_applicable(name, &flags)?;
+                Self::command(name, rest, args).map(Parsed::Run)
+            }
+        }
+    }
+
+    // every flag in args is known to apply to this command by now
+    fn command(name: &str, rest: &[String], args: &[String]) -> Result<Command, String> {
+        let command = match name {
+            "build" => {
+                Self::expect_no_more(name, rest)?;
+                let prune = Self::has_flag(args, &["--prune"]);
+                let dry_run = Self::has_flag(args, &["--dry-run"]);
+                if dry_run && !prune {
+                    return Err("--dry-run only works together with --prune".to_string());
+                }
+                Command::Build {
+                    upsert_only: Self::has_flag(args, &["--upsert-only", "-u"]),
+                    prune,
+                    dry_run,
+                    force: Self::has_flag(args, &["--force", "-f"]),
+                }
+            }
+            "new" => match rest {
+                [title] => Command::New {
+                    title: title.clone(),
+                },
+                [] => return Err("Missing <TITLE> for 'new'".to_string()),
+                _ => {
+                    return Err("Too many arguments for 'new', put the title in quotes".to_string());
+                }
+            },
+            "check" => {
+                Self::expect_no_more(name, rest)?;
+                Command::Check
+            }
+            "clean" => {
+                Self::expect_no_more(name, rest)?;
+                Command::Clean
+            }
+            "watch" => {
+                Self::expect_no_more(name, rest)?;
+                Command::Watch
+            }
+            "suggest-tags" => {
+                Self::expect_no_more(name, rest)?;
+                let count = match Self::flag_value(args, &["--count", "-n"]) {
+                    Some(n) => n
+                        .parse::<usize>()
+                        .map_err(|_| format!("Invalid count '{}'", n))?,
+                    None => DEFAULT_SUGGESTION_COUNT,
+                };
+                Command::SuggestTags {
+                    count,
+                    write: Self::has_flag(args, &["--write"]),
+                }
+            }
+            "serve" => {
+                Self::expect_no_more(name, rest)?;
+                let port = match Self::flag_value(args, &["--port", "-p"]) {
+                    Some(p) => p
+                        .parse::<u16>()
+                        .map_err(|_| format!("Invalid port '{}'", p))?,
+                    None => DEFAULT_PORT,
+                };
+                Command::Serve { port }
+            }
+            _ => return Err(format!("Unknown command '{}'", name)),
+        };
+
+        Ok(command)
+    }
+
+    pub fn main_help() -> &'static str {
+        MAIN_HELP
+    }
+
+    fn help_for(name: &str) -> Option<&'static str> {
+        match name {
+            "build" => Some(BUILD_HELP),
+            "new" => Some(NEW_HELP),
+            "check" => Some(CHECK_HELP),
+            "clean" => Some(CLEAN_HELP),
+            "watch" => Some(WATCH_HELP),
+            "suggest-tags" => Some(SUGGEST_TAGS_HELP),
+            "serve" => Some(SERVE_HELP),
+            "help" => Some(MAIN_HELP),
+            _ => None,
+        }
+    }
+
+    // (everything that isn't a flag or the value of a flag, the names of the flags)
+    fn split_args(args: &[String]) -> Result<(Vec<String>, Vec<String>), String> {
+        let mut positional = Vec::new();
+        let mut flags = Vec::new();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let arg_str = arg.as_str();
+            if CONFIG_FLAGS.contains(&arg_str)
+                || matches!(arg_str, "--port" | "-p" | "--count" | "-n")
+            {
+                if iter.next().is_none() {
+                    return Err(format!("Missing value for {}", arg));
+                }
+                flags.push(arg.clone());
+            } else if arg_str.starts_with('-') && arg_str.len() > 1 {
+                if !Self::is_known_flag(arg_str) {
+                    return Err(format!("Unknown option '{}'", arg));
+                }
+                flags.push(arg_str.split('=').next().unwrap_or(arg_str).to_string());
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+        Ok((positional, flags))
+    }
+
+    // the flags only some commands take, on top of the config flags and help
+    fn command_flags(name: &str) -> &'static [&'static str] {
+        match name {
+            "build" => &[
+                "--upsert-only",
+                "-u",
+                "--prune",
+                "--dry-run",
+                "--force",
+                "-f",
+            ],
+            "suggest-tags" => &["--count", "-n", "--write"],
+            "serve" => &["--port", "-p"],
+            _ => &[],
+        }
+    }
+
+    // a known flag on the wrong command is a mistake too, rather than silently ignored
+    fn expect_applicable(name: &str, flags: &[String]) -> Result<(), String> {
+        let command_flags = Self::command_flags(name);
+        for flag in flags {
+            let flag = flag.as_str();
+            if !CONFIG_FLAGS.contains(&flag)
+                && !matches!(flag, "--help" | "-h")
+                && !command_flags.contains(&flag)
+            {
+                return Err(format!("Option '{}' doesn't apply to '{}'", flag, name));
+            }
+        }
+        Ok(())
+    }
+
+    fn is_known_flag(arg: &str) -> bool {
+        let name = arg.split('=').next().unwrap_or(arg);
+        CONFIG_FLAGS.contains(&name)
+            || matches!(
+                name,
+                "--help"
+                    | "-h"
+                    | "--upsert-only"
+                    | "-u"
+                    | "--prune"
+                    | "--dry-run"
+                    | "--force"
+                    | "-f"
+                    | "--port"
+                    | "-p"
+                    | "--count"
+                    | "-n"
+                    | "--write"
+            )
+    }
+
+    fn expect_no_more(name: &str, rest: &[String]) -> Result<(), String> {
+        match rest.first() {
+            Some(extra) => Err(format!("Unexpected argument '{}' for '{}'", extra, name)),
+            None => Ok(()),
+        }
+    }
+
+    fn has_flag(args: &[String], names: &[&str]) -> bool {
+        args.iter().any(|a| names.contains(&a.as_str()))
+    }
+
+    // accepts both "--flag value" and "--flag=value"
+    pub fn flag_value(args: &[String], names: &[&str]) -> Option<String> {
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            for name in names {
+                if arg == name {
+                    return iter.next().cloned();
+                }
+                if let Some(v) = arg.strip_prefix(&format!("{}=", name)) {
+                    return Some(v.to_string());
+                }
+            }
+        }
+        None
+    }
+}
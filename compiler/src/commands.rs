@@ -0,0 +1,605 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::{
+    articles::Articles,
+    config::Config,
+    error::{Error, Result},
+    feed::Feeds,
+    front_matter::FrontMatter,
+    html::IndexHtml,
+    info::InfoWrangler,
+    sitemap::Sitemap,
+    tags::TagPages,
+    tfidf::{Document, TfIdf},
+    utils::Utils,
+    watch::Watcher,
+};
+
+pub struct Commands {}
+
+impl Commands {
+    pub fn build(
+        config: &Config,
+        upsert_only: bool,
+        prune: bool,
+        dry_run: bool,
+        force: bool,
+    ) -> ExitCode {
+        println!("generating static site...");
+
+        match Self::try_build(config, upsert_only, prune, dry_run, force) {
+            Ok(failures) if failures.is_empty() => {
+                println!("done!");
+                ExitCode::SUCCESS
+            }
+            Ok(failures) => {
+                Self::report_failures("the build", &failures);
+                ExitCode::FAILURE
+            }
+            Err(e) => {
+                eprintln!("error: {}", e);
+                ExitCode::FAILURE
+            }
+        }
+    }
+
+    // problems with a single article are collected and returned, so the rest can still be built
+    // anything else stops the build right away
+    fn try_build(
+        config: &Config,
+        upsert_only: bool,
+        prune: bool,
+        dry_run: bool,
+        force: bool,
+    ) -> Result<Vec<Error>> {
+        let mut info_wrangler = InfoWrangler::init(config)?;
+        Articles::create_input_dir(config)?;
+        let article_paths = Articles::get_article_paths(config)?;
+
+        let (mut failures, failed_paths) = Self::upsert_all(&mut info_wrangler, &article_paths);
+
+        for redirect in info_wrangler.detect_renames(&article_paths) {
+            println!(
+                "detected rename: {}.html -> {}.html",
+                redirect.from, redirect.to
+            );
+        }
+
+        if prune && dry_run {
+            let stale = info_wrangler.get_stale();
+            for article in &stale {
+                println!(
+                    "would prune '{}' ({})",
+                    article.original_filename,
+                    Self::output_html_path(config, &article.safe_filename).display()
+                );
+            }
+            println!(
+                "{} article(s) would be pruned, nothing was written",
+                stale.len()
+            );
+            return Ok(failures);
+        }
+
+        if prune && !failed_paths.is_empty() {
+            // an article that failed to load looks exactly like a deleted one
+            println!("not pruning, because some articles failed to load");
+        } else if prune {
+            Self::prune(config, &mut info_wrangler);
+        } else {
+            let stale_count = info_wrangler.get_stale().len();
+            if stale_count > 0 {
+                println!(
+                    "{} tracked article(s) have no source file, use --prune to remove them",
+                    stale_count
+                );
+            }
+        }
+
+        if upsert_only {
+            info_wrangler.save()?;
+            return Ok(failures);
+        }
+
+        // pages link to their chronological neighbours
+        info_wrangler.sort_created_at();
+
+        let mut rendered = 0;
+        let mut unchanged = 0;
+        for path in &article_paths {
+            if failed_paths.contains(path.as_path()) {
+                continue;
+            }
+            match Articles::process(config, path, &mut info_wrangler, force) {
+                Ok(true) => rendered += 1,
+                Ok(false) => unchanged += 1,
+                Err(e) => failures.push(e),
+            }
+        }
+        println!(
+            "rendered {} article(s), skipped {} unchanged or draft",
+            rendered, unchanged
+        );
+
+        Self::save_site(config, &mut info_wrangler, &mut failures)?;
+
+        Ok(failures)
+    }
+
+    // rebuild after some files changed, for watch and serve
+    // every article is upserted, but only the changed ones (and those whose page is otherwise
+    // out of date, like a neighbour's) are processed
+    // unless something other than an article changed, then everything is (still incrementally)
+    // prints a one line summary
+    pub fn rebuild(config: &Config, changed: &[PathBuf]) {
+        let started = std::time::Instant::now();
+        let time = chrono::Local::now().format("%H:%M:%S");
+
+        match Self::try_rebuild(config, changed) {
+            Ok((rendered, failures)) => {
+                println!(
+                    "[{}] {} file(s) changed, rendered {} article(s), {} error(s) in {}ms",
+                    time,
+                    changed.len(),
+                    rendered,
+                    failures.len(),
+                    started.elapsed().as_millis()
+                );
+                for failure in &failures {
+                    eprintln!("  error: {}", failure);
+                }
+            }
+            Err(e) => {
+                println!("[{}] {} file(s) changed, build failed", time, changed.len());
+                eprintln!("  error: {}", e);
+            }
+        }
+    }
+
+    fn try_rebuild(config: &Config, changed: &[PathBuf]) -> Result<(usize, Vec<Error>)> {
+        let mut info_wrangler = InfoWrangler::init(config)?;
+        let article_paths = Articles::get_article_paths(config)?;
+
+        let (mut failures, failed_paths) = Self::upsert_all(&mut info_wrangler, &article_paths);
+        info_wrangler.detect_renames(&article_paths);
+
+        info_wrangler.sort_created_at();
+
+        let changed: HashSet<&Path> = changed.iter().map(|p| p.as_path()).collect();
+        let is_article = |path: &&Path| article_paths.iter().any(|a| a == path);
+        let only_articles_changed = changed.iter().all(|path| {
+            is_article(path) || (!path.exists() && path.starts_with(&config.input_dir))
+        });
+
+        let mut rendered = 0;
+        for path in &article_paths {
+            if failed_paths.contains(path.as_path()) {
+                continue;
+            }
+            // other articles only need rendering if e.g. a neighbour's title changed
+            if only_articles_changed
+                && !changed.contains(path.as_path())
+                && !Articles::needs_render(config, path, &info_wrangler)
+            {
+                continue;
+            }
+            match Articles::process(config, path, &mut info_wrangler, false) {
+                Ok(true) => rendered += 1,
+                Ok(false) => {}
+                Err(e) => failures.push(e),
+            }
+        }
+
+        Self::save_site(config, &mut info_wrangler, &mut failures)?;
+
+        Ok((rendered, failures))
+    }
+
+    // build once, then rebuild whenever an article or the info file changes
+    pub fn watch(config: &Config) -> ExitCode {
+        let _ = Self::build(config, false, false, false, false);
+
+        let mut watcher = Watcher::new(vec![config.input_dir.clone(), config.info_file.clone()]);
+        println!(
+            "watching {:?} and {:?} for changes (ctrl-c to stop)",
+            config.input_dir, config.info_file
+        );
+
+        loop {
+            let changed = watcher.wait_for_changes();
+            Self::rebuild(config, &changed);
+            // the build writes the info file, that isn't a change worth another build
+            watcher.refresh();
+        }
+    }
+
+    // upserts every article, a problem with one is collected instead of stopping the others
+    // returns the problems, and the paths that had them (they look deleted to everything after)
+    fn upsert_all<'a>(
+        info_wrangler: &mut InfoWrangler,
+        article_paths: &'a [PathBuf],
+    ) -> (Vec<Error>, HashSet<&'a Path>) {
+        let mut failures = Vec::new();
+        let mut failed_paths = HashSet::new();
+        for path in article_paths {
+            if let Err(e) = Self::upsert(info_wrangler, path) {
+                failures.push(e);
+                failed_paths.insert(path.as_path());
+            }
+        }
+        (failures, failed_paths)
+    }
+
+    // everything after the article pages, shared by build and rebuild so they stay the same:
+    // redirects, the info file, the index, feeds, tag pages and the sitemap
+    fn save_site(
+        config: &Config,
+        info_wrangler: &mut InfoWrangler,
+        failures: &mut Vec<Error>,
+    ) -> Result<()> {
+        for redirect in info_wrangler.get_redirects() {
+            if let Err(e) = Articles::save_redirect(config, redirect) {
+                failures.push(e);
+            }
+        }
+
+        info_wrangler.save()?;
+        IndexHtml::save(config, info_wrangler)?;
+        failures.extend(Feeds::save(config, info_wrangler)?);
+        TagPages::save(config, info_wrangler)?;
+        Sitemap::save(config, info_wrangler)
+    }
+
+    fn upsert(info_wrangler: &mut InfoWrangler, path: &Path) -> Result<()> {
+        let front_matter = Articles::read_front_matter(path)?;
+        info_wrangler.upsert(path, &front_matter)
+    }
+
+    fn report_failures(during: &str, failures: &[Error]) {
+        eprintln!("{} problem(s) during {}:", failures.len(), during);
+        for failure in failures {
+            eprintln!("  error: {}", failure);
+        }
+    }
+
+    fn prune(config: &Config, info_wrangler: &mut InfoWrangler) {
+        let (pruned, dropped_redirects) = info_wrangler.prune();
+        for article in pruned {
+            let html_path = Self::output_html_path(config, &article.safe_filename);
+            match std::fs::remove_file(&html_path) {
+                Ok(_) => println!(
+                    "pruned '{}', deleted {}",
+                    article.original_filename,
+                    html_path.display()
+                ),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    println!("pruned '{}'", article.original_filename)
+                }
+                Err(e) => eprintln!(
+                    "warning: pruned '{}' but failed to delete {}: {}",
+                    article.original_filename,
+                    html_path.display(),
+                    e
+                ),
+            }
+        }
+
+        // their stub pages would only redirect to a 404 now
+        for redirect in dropped_redirects {
+            let html_path = Self::output_html_path(config, &redirect.from);
+            match std::fs::remove_file(&html_path) {
+                Ok(_) => println!("deleted redirect {}", html_path.display()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => eprintln!(
+                    "warning: failed to delete redirect {}: {}",
+                    html_path.display(),
+                    e
+                ),
+            }
+        }
+    }
+
+    fn output_html_path(config: &Config, safe_filename: &str) -> PathBuf {
+        config.output_dir.join(format!("{}.html", safe_filename))
+    }
+
+    pub fn new_article(config: &Config, title: &str) -> ExitCode {
+        match Self::try_new_article(config, title) {
+            Ok(path) => {
+                println!(
+                    "created {:?} as a draft, remove 'draft: true' to publish it",
+                    path
+                );
+                ExitCode::SUCCESS
+            }
+            Err(message) => {
+                eprintln!("error: {}", message);
+                ExitCode::FAILURE
+            }
+        }
+    }
+
+    fn try_new_article(config: &Config, title: &str) -> std::result::Result<PathBuf, String> {
+        let title = title.trim();
+        // the title is the file name too, and that has to stay directly in input_dir
+        if title.contains(std::path::is_separator) {
+            return Err(format!(
+                "'{}' can't contain a path separator, it's also the file name",
+                title
+            ));
+        }
+        let safe_filename = match Utils::format_safe_filename(title) {
+            Some(name) if !name.is_empty() => name,
+            _ => {
+                return Err(format!(
+                    "'{}' doesn't contain any url-safe characters",
+                    title
+                ));
+            }
+        };
+
+        let mut info_wrangler = InfoWrangler::init(config).map_err(|e| e.to_string())?;
+        Articles::create_input_dir(config).map_err(|e| e.to_string())?;
+        let article_paths = Articles::get_article_paths(config).map_err(|e| e.to_string())?;
+
+        for path in &article_paths {
+            let relative_path = path.strip_prefix(&config.input_dir).unwrap_or(path);
+            let existing = Articles::read_front_matter(path)
+                .ok()
+                .and_then(|fm| fm.safe_filename(relative_path));
+            if existing.as_deref() == Some(safe_filename.as_str()) {
+                return Err(format!(
+                    "{:?} already exists with the same url ({}.html)",
+                    path, safe_filename
+                ));
+            }
+        }
+
+        // starts as a draft, so it isn't published half-written
+        let today = chrono::Local::now().format("%Y-%m-%d");
+        let path = config.input_dir.join(format!("{}.md", title));
+        let content = format!("---\ndate: {}\ndraft: true\n---\n\n# {}\n\n", today, title);
+        // a file with another slug in its front matter wasn't caught above, never overwrite it
+        let written = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(content.as_bytes()));
+        match written {
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Err(format!("{:?} already exists", path));
+            }
+            Err(e) => return Err(format!("failed to create {:?}: {}", path, e)),
+            Ok(()) => {}
+        }
+
+        let (front_matter, _) = FrontMatter::parse(&content)?;
+        info_wrangler
+            .upsert(&path, &front_matter)
+            .map_err(|e| e.to_string())?;
+        info_wrangler.save().map_err(|e| e.to_string())?;
+
+        Ok(path)
+    }
+
+    // does everything build does, except write
+    pub fn check(config: &Config) -> ExitCode {
+        let mut errors: Vec<String> = Vec::new();
+        let mut warnings: Vec<String> = Vec::new();
+
+        let loaded = InfoWrangler::init(config)
+            .and_then(|info| Articles::get_article_paths(config).map(|paths| (info, paths)));
+        let (mut info_wrangler, article_paths) = match loaded {
+            Ok(v) => v,
+            Err(e) => {
+                errors.push(e.to_string());
+                return Self::report_check(&errors, &warnings);
+            }
+        };
+
+        let (failures, failed_paths) = Self::upsert_all(&mut info_wrangler, &article_paths);
+        errors.extend(failures.iter().map(|e| e.to_string()));
+
+        for redirect in info_wrangler.detect_renames(&article_paths) {
+            println!(
+                "note: looks like {}.html was renamed to {}.html, build will add a redirect",
+                redirect.from, redirect.to
+            );
+        }
+
+        if failed_paths.is_empty() {
+            for article in info_wrangler.get_stale() {
+                warnings.push(format!(
+                    "'{}' is tracked in {:?} but has no source file (build --prune removes it)",
+                    article.original_filename, config.info_file
+                ));
+            }
+        }
+
+        info_wrangler.sort_created_at();
+        for path in &article_paths {
+            if failed_paths.contains(path.as_path()) {
+                continue;
+            }
+            match Articles::render(config, path, &mut info_wrangler) {
+                Ok((_, html)) if html.is_empty() => {
+                    errors.push(format!("{:?}: rendered to nothing", path));
+                }
+                Ok(_) => {}
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+
+        Self::report_check(&errors, &warnings)
+    }
+
+    fn report_check(errors: &[String], warnings: &[String]) -> ExitCode {
+        for warning in warnings {
+            println!("warning: {}", warning);
+        }
+        for error in errors {
+            println!("error: {}", error);
+        }
+
+        println!(
+            "check finished with {} error(s) and {} warning(s)",
+            errors.len(),
+            warnings.len()
+        );
+
+        if errors.is_empty() {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        }
+    }
+
+    // prints suggested tags for every article, and with write, adds them to the info file
+    // tags in front matter replace the info file's, so those articles can only be shown their suggestions
+    pub fn suggest_tags(config: &Config, count: usize, write: bool) -> ExitCode {
+        match Self::try_suggest_tags(config, count, write) {
+            Ok(failures) if failures.is_empty() => ExitCode::SUCCESS,
+            Ok(failures) => {
+                Self::report_failures("tag suggestions", &failures);
+                ExitCode::FAILURE
+            }
+            Err(e) => {
+                eprintln!("error: {}", e);
+                ExitCode::FAILURE
+            }
+        }
+    }
+
+    // like try_build, problems with a single article are collected and returned,
+    // the others still get their suggestions
+    fn try_suggest_tags(config: &Config, count: usize, write: bool) -> Result<Vec<Error>> {
+        let mut info_wrangler = InfoWrangler::init(config)?;
+        let article_paths = Articles::get_article_paths(config)?;
+
+        let (mut failures, failed_paths) = Self::upsert_all(&mut info_wrangler, &article_paths);
+
+        // (path, plain text, whether its tags come from the front matter)
+        let mut articles = Vec::new();
+        for path in &article_paths {
+            if failed_paths.contains(path.as_path()) {
+                continue;
+            }
+            let loaded = Articles::read_front_matter(path).and_then(|front_matter| {
+                let text = Articles::read_plain_text(config, path)?;
+                Ok((text, front_matter.tags.is_some()))
+            });
+            match loaded {
+                Ok((text, tags_in_front_matter)) => {
+                    articles.push((path, text, tags_in_front_matter))
+                }
+                Err(e) => failures.push(e),
+            }
+        }
+        if write {
+            // so a renamed article keeps its entry, instead of being saved as a new one
+            info_wrangler.detect_renames(&article_paths);
+        }
+
+        let no_tags = Vec::new();
+        let documents: Vec<Document> = articles
+            .iter()
+            .map(|(path, text, _)| Document {
+                text,
+                existing_tags: info_wrangler
+                    .get_article(path)
+                    .map(|article| &article.tags)
+                    .unwrap_or(&no_tags),
+            })
+            .collect();
+        let suggestions = TfIdf::suggest(&documents, count);
+
+        let mut written = 0;
+        for ((path, _, tags_in_front_matter), suggested) in articles.iter().zip(suggestions) {
+            let Some(article) = info_wrangler.get_article(path) else {
+                continue;
+            };
+            println!("{}: {}", article.title(), suggested.join(", "));
+            if !write || suggested.is_empty() {
+                continue;
+            }
+            if *tags_in_front_matter {
+                println!(
+                    "  not written, {:?} sets its tags in its front matter",
+                    path
+                );
+                continue;
+            }
+
+            let mut tags = article.tags.clone();
+            tags.extend(suggested);
+            info_wrangler.set_tags(path, tags);
+            written += 1;
+        }
+
+        if write {
+            info_wrangler.save()?;
+            println!(
+                "added suggestions to {} article(s) in {:?}",
+                written, config.info_file
+            );
+        }
+        Ok(failures)
+    }
+
+    pub fn clean(config: &Config) -> ExitCode {
+        let read_dir = match std::fs::read_dir(&config.output_dir) {
+            Ok(rd) => rd,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                println!("nothing to clean");
+                return ExitCode::SUCCESS;
+            }
+            Err(e) => {
+                eprintln!(
+                    "error: {}",
+                    Error::io("read output directory", &config.output_dir, e)
+                );
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let mut removed = 0;
+        let mut failed = false;
+        for entry in read_dir {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    eprintln!(
+                        "error: {}",
+                        Error::io("read output directory", &config.output_dir, e)
+                    );
+                    failed = true;
+                    continue;
+                }
+            };
+
+            let result = if path.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+
+            match result {
+                Ok(_) => removed += 1,
+                Err(e) => {
+                    eprintln!("error: {}", Error::io("remove", &path, e));
+                    failed = true;
+                }
+            }
+        }
+
+        println!("removed {} item(s) from {:?}", removed, config.output_dir);
+        if failed {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
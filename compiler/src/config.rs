@@ -0,0 +1,146 @@
+use crate::cli::Cli;
+use crate::error::{Error, Result};
+use crate::feed::FeedOptions;
+use crate::highlight::{HighlightOptions, Highlighter};
+use crate::markdown::MarkdownOptions;
+use crate::search::{Search, SearchOptions};
+use crate::sitemap::SitemapOptions;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_CONFIG_FILE_NAME: &str = "blag.toml";
+
+// everything that used to be hard-coded, so one binary can build any site
+// values come from (in increasing priority): defaults, blag.toml, CLI flags
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub info_file: PathBuf,  // tracking info, relative to the working directory
+    pub input_dir: PathBuf,  // markdown articles are read from here
+    pub output_dir: PathBuf, // html is written here
+    pub title: String,       // site title, shown on the index page
+    pub base_path: String,   // url prefix the site is served under, like "/blag"
+    pub source_url: Option<String>, // link to the site's source, if any
+    pub site_url: Option<String>, // where the site is hosted, like "https://example.com", for absolute urls
+    pub extensions: Vec<String>,  // only files with these extensions are articles
+    pub ignore_file: String,      // gitignore-style patterns in input_dir, of files to skip
+    pub markdown: MarkdownOptions, // CommonMark extensions, articles can override these
+    pub highlight: HighlightOptions, // syntax highlighting of fenced code blocks
+    pub feed: FeedOptions,        // atom, rss and json feeds, need site_url
+    pub sitemap: SitemapOptions,  // sitemap.xml and robots.txt, need site_url
+    pub search: SearchOptions,    // how the index page's search ranks articles
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            info_file: PathBuf::from("articles.json"),
+            input_dir: PathBuf::from("../articles"),
+            output_dir: PathBuf::from("../docs"),
+            title: "Bug Blog".to_string(),
+            base_path: "/blag".to_string(),
+            source_url: None,
+            site_url: None,
+            extensions: vec!["md".to_string(), "markdown".to_string()],
+            ignore_file: ".blagignore".to_string(),
+            markdown: MarkdownOptions::default(),
+            highlight: HighlightOptions::default(),
+            feed: FeedOptions::default(),
+            sitemap: SitemapOptions::default(),
+            search: SearchOptions::default(),
+        }
+    }
+}
+
+impl Config {
+    // load the config file, then apply any overrides from the command line
+    // a missing config file is fine if it wasn't explicitly asked for
+    pub fn load(args: &[String]) -> Result<Self> {
+        let explicit_path = Cli::flag_value(args, &["--config", "-c"]);
+        let path = match &explicit_path {
+            Some(p) => PathBuf::from(p),
+            None => PathBuf::from(DEFAULT_CONFIG_FILE_NAME),
+        };
+
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&path, &content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && explicit_path.is_none() => {
+                Config::default()
+            }
+            Err(e) => return Err(Error::io("read config file", &path, e)),
+        };
+
+        config.apply_overrides(args);
+        config.base_path = Self::normalize_base_path(&config.base_path);
+        Highlighter::validate(&config.highlight).map_err(|message| Error::Config {
+            path: path.clone(),
+            message,
+        })?;
+        Search::validate(&config.search).map_err(|message| Error::Config {
+            path: path.clone(),
+            message,
+        })?;
+        Ok(config)
+    }
+
+    fn parse(path: &Path, content: &str) -> Result<Self> {
+        toml::from_str::<Config>(content).map_err(|e| Error::Config {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })
+    }
+
+    fn apply_overrides(&mut self, args: &[String]) {
+        if let Some(v) = Cli::flag_value(args, &["--info-file"]) {
+            self.info_file = PathBuf::from(v);
+        }
+        if let Some(v) = Cli::flag_value(args, &["--input-dir"]) {
+            self.input_dir = PathBuf::from(v);
+        }
+        if let Some(v) = Cli::flag_value(args, &["--output-dir"]) {
+            self.output_dir = PathBuf::from(v);
+        }
+        if let Some(v) = Cli::flag_value(args, &["--title"]) {
+            self.title = v;
+        }
+        if let Some(v) = Cli::flag_value(args, &["--base-path"]) {
+            self.base_path = v;
+        }
+        if let Some(v) = Cli::flag_value(args, &["--source-url"]) {
+            self.source_url = if v.is_empty() { None } else { Some(v) };
+        }
+        if let Some(v) = Cli::flag_value(args, &["--site-url"]) {
+            self.site_url = if v.is_empty() { None } else { Some(v) };
+        }
+    }
+
+    // every setting that changes how an article page is rendered
+    // articles rendered with a different fingerprint are rendered again on the next build
+    pub fn render_fingerprint(&self) -> String {
+        format!(
+            "base_path={}\nmarkdown={}\nhighlight={}",
+            self.base_path,
+            self.markdown.fingerprint(),
+            Highlighter::fingerprint(&self.highlight)
+        )
+    }
+
+    // absolute url of the site, like "https://example.com/blag", without a trailing /
+    pub fn site_root(&self) -> Option<String> {
+        let site_url = self.site_url.as_deref()?.trim().trim_end_matches('/');
+        if site_url.is_empty() {
+            return None;
+        }
+        Some(format!("{}{}", site_url, self.base_path))
+    }
+
+    // "blag", "/blag/" and "/blag" all become "/blag"; "" and "/" become ""
+    fn normalize_base_path(base_path: &str) -> String {
+        let trimmed = base_path.trim().trim_matches('/');
+        if trimmed.is_empty() {
+            String::new()
+        } else {
+            format!("/{}", trimmed)
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::path::{Path, PathBuf};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// everything that can go wrong while building, each tied to the file it happened to
+pub enum Error {
+    // reading, writing, creating or deleting a file or directory failed
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    // a JSON file couldn't be read or written
+    Json {
+        action: &'static str,
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    Config {
+        path: PathBuf,
+        message: String,
+    },
+    FrontMatter {
+        path: PathBuf,
+        message: String,
+    },
+    // two source files would be written to the same html file
+    DuplicateSafeFilename {
+        safe_filename: String,
+        path: PathBuf,
+    },
+    // an article would be written where the site's own pages go, like tags/
+    ReservedSafeFilename {
+        safe_filename: String,
+        path: PathBuf,
+    },
+    // a source file was never upserted into the info file
+    MissingArticleInfo {
+        path: PathBuf,
+    },
+}
+
+impl Error {
+    pub fn io(action: &'static str, path: &Path, source: std::io::Error) -> Self {
+        Error::Io {
+            action,
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn json(action: &'static str, path: &Path, source: serde_json::Error) -> Self {
+        Error::Json {
+            action,
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io {
+                action,
+                path,
+                source,
+            } => write!(f, "{:?}: failed to {}: {}", path, action, source),
+            Error::Json {
+                action,
+                path,
+                source,
+            } => write!(f, "{:?}: failed to {}: {}", path, action, source),
+            Error::Config { path, message } => write!(f, "{:?}: bad config: {}", path, message),
+            Error::FrontMatter { path, message } => {
+                write!(f, "{:?}: bad front matter: {}", path, message)
+            }
+            Error::DuplicateSafeFilename {
+                safe_filename,
+                path,
+            } => write!(
+                f,
+                "{:?}: another article is already written to {}.html",
+                path, safe_filename
+            ),
+            Error::ReservedSafeFilename {
+                safe_filename,
+                path,
+            } => write!(
+                f,
+                "{:?}: {}.html is kept for the site's own pages, move the article to another folder",
+                path, safe_filename
+            ),
+            Error::MissingArticleInfo { path } => {
+                write!(f, "{:?}: article is not tracked in the info file", path)
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
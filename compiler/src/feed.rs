@@ -0,0 +1,379 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::{
+    articles::Articles,
+    config::Config,
+    error::{Error, Result},
+    info::{ArticleInfo, InfoWrangler},
+    utils::Utils,
+};
+
+// feed.xml (atom), rss.xml and feed.json, so readers can subscribe
+// feeds need absolute urls, so they're only written when site_url is set
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeedOptions {
+    pub enabled: bool,
+    pub limit: usize,       // only the newest articles are in the feeds
+    pub full_content: bool, // the whole article, instead of just its summary
+}
+
+impl Default for FeedOptions {
+    fn default() -> Self {
+        FeedOptions {
+            enabled: true,
+            limit: 20,
+            full_content: false,
+        }
+    }
+}
+
+// how long an excerpt can get, for articles without a summary
+const EXCERPT_CHARS: usize = 280;
+
+// one article, as it shows up in a feed
+struct FeedItem<'a> {
+    article: &'a ArticleInfo,
+    url: String,
+    content: Option<String>, // rendered html, if full_content is set
+    summary: Option<String>, // the front matter's summary, or else the start of the article, in every feed
+}
+
+// https://www.jsonfeed.org/version/1.1/
+#[derive(Serialize)]
+struct JsonFeed {
+    version: &'static str,
+    title: String,
+    home_page_url: String,
+    feed_url: String,
+    items: Vec<JsonFeedItem>,
+}
+
+// what's reused from the last build's feed.json, see Feeds::previous_content
+#[derive(Deserialize)]
+struct PreviousJsonFeed {
+    items: Vec<PreviousJsonFeedItem>,
+}
+
+#[derive(Deserialize)]
+struct PreviousJsonFeedItem {
+    id: String,
+    content_html: Option<String>,
+    date_modified: String,
+}
+
+#[derive(Serialize)]
+struct JsonFeedItem {
+    id: String,
+    url: String,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_html: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_text: Option<String>, // only when there's no html, an item needs one or the other
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    date_published: String,
+    date_modified: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+}
+
+pub struct Feeds {}
+
+impl Feeds {
+    // problems with a single article are returned, that article's item just has less in it
+    pub fn save(config: &Config, info_wrangler: &mut InfoWrangler) -> Result<Vec<Error>> {
+        if !config.feed.enabled {
+            return Ok(vec![]);
+        }
+        let Some(site_root) = config.site_root() else {
+            return Ok(vec![]);
+        };
+
+        info_wrangler.sort_created_at();
+        let json_path = config.output_dir.join("feed.json");
+        let mut failures = Vec::new();
+        let items = Self::items(config, info_wrangler, &site_root, &json_path, &mut failures);
+
+        Utils::write_file(
+            &config.output_dir.join("feed.xml"),
+            Self::atom(config, &site_root, &items),
+        )?;
+        Utils::write_file(
+            &config.output_dir.join("rss.xml"),
+            Self::rss(config, &site_root, &items),
+        )?;
+
+        let json = Self::json(config, &site_root, &items)
+            .map_err(|e| Error::json("serialize json feed", &json_path, e))?;
+        Utils::write_file(&json_path, json)?;
+        Ok(failures)
+    }
+
+    fn items<'a>(
+        config: &Config,
+        info_wrangler: &'a InfoWrangler,
+        site_root: &str,
+        json_path: &Path,
+        failures: &mut Vec<Error>,
+    ) -> Vec<FeedItem<'a>> {
+        let mut previous_content = match config.feed.full_content {
+            true => Self::previous_content(json_path),
+            false => HashMap::new(),
+        };
+
+        let mut items = Vec::new();
+        for article in info_wrangler
+            .get_published()
+            .into_iter()
+            .take(config.feed.limit)
+        {
+            let url = format!("{}/{}.html", site_root, article.safe_filename);
+            let date_modified = Self::date_modified(article);
+            // articles whose source is gone (but aren't pruned yet) only have what the info file has
+            let path = info_wrangler.get_path(&article.safe_filename);
+            let content = match path {
+                Some(path) if config.feed.full_content => {
+                    match (&article.body_html, previous_content.remove(&url)) {
+                        // rendered this run
+                        (Some(html), _) => Some(html.clone()),
+                        // not rendered this run, so the last build's is still right if it's as new
+                        (None, Some((html, modified))) if modified == date_modified => Some(html),
+                        _ => Articles::render_body_html(config, path)
+                            .map_err(|e| failures.push(e))
+                            .ok(),
+                    }
+                }
+                _ => None,
+            };
+            let summary = match (&article.summary, path) {
+                (Some(summary), _) => Some(summary.clone()),
+                (None, Some(path)) => Articles::read_plain_text(config, path)
+                    .map_err(|e| failures.push(e))
+                    .ok()
+                    .and_then(|text| Self::excerpt(&text)),
+                (None, None) => None,
+            };
+            items.push(FeedItem {
+                article,
+                url,
+                content,
+                summary,
+            });
+        }
+        items
+    }
+
+    // url -> (content_html, date_modified) of the last build's json feed, nothing if it can't be read
+    fn previous_content(json_path: &Path) -> HashMap<String, (String, String)> {
+        std::fs::read(json_path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<PreviousJsonFeed>(&bytes).ok())
+            .map(|feed| {
+                feed.items
+                    .into_iter()
+                    .filter_map(|item| Some((item.id, (item.content_html?, item.date_modified))))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn date_modified(article: &ArticleInfo) -> String {
+        Utils::format_rfc3339(article.updated_at.max(article.created_at)).unwrap_or_default()
+    }
+
+    // the start of the article's text, cut at a word, None if there's no text at all
+    fn excerpt(text: &str) -> Option<String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let mut excerpt = String::new();
+        for word in &words {
+            if !excerpt.is_empty()
+                && excerpt.chars().count() + 1 + word.chars().count() > EXCERPT_CHARS
+            {
+                excerpt.push('…');
+                return Some(excerpt);
+            }
+            if !excerpt.is_empty() {
+                excerpt.push(' ');
+            }
+            excerpt.push_str(word);
+        }
+        Some(excerpt).filter(|excerpt| !excerpt.is_empty())
+    }
+
+    // the newest update of anything in the feed
+    fn last_updated(items: &[FeedItem]) -> u64 {
+        items
+            .iter()
+            .map(|item| item.article.updated_at.max(item.article.created_at))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn atom(config: &Config, site_root: &str, items: &[FeedItem]) -> String {
+        let site_root = Utils::escape_html(site_root);
+        let title = Utils::escape_html(&config.title);
+        let mut xml = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+    <title>{}</title>
+    <id>{}/</id>
+    <link href="{}/"/>
+    <link rel="self" type="application/atom+xml" href="{}/feed.xml"/>
+    <updated>{}</updated>
+    <author><name>{}</name></author>
+"#,
+            title,
+            site_root,
+            site_root,
+            site_root,
+            Utils::format_rfc3339(Self::last_updated(items)).unwrap_or_default(),
+            title,
+        );
+
+        for item in items {
+            let article = item.article;
+            let categories: String = article
+                .tags
+                .iter()
+                .map(|tag| format!("        <category term=\"{}\"/>\n", Utils::escape_html(tag)))
+                .collect();
+            let summary = match &item.summary {
+                Some(summary) => format!(
+                    "        <summary>{}</summary>\n",
+                    Utils::escape_html(summary)
+                ),
+                None => String::new(),
+            };
+            let content = match &item.content {
+                Some(html) => format!(
+                    "        <content type=\"html\">{}</content>\n",
+                    Utils::escape_html(html)
+                ),
+                None => String::new(),
+            };
+
+            xml.push_str(&format!(
+                r#"    <entry>
+        <title>{}</title>
+        <id>{}</id>
+        <link href="{}"/>
+        <published>{}</published>
+        <updated>{}</updated>
+{}{}{}    </entry>
+"#,
+                Utils::escape_html(article.title()),
+                Utils::escape_html(&item.url),
+                Utils::escape_html(&item.url),
+                Utils::format_rfc3339(article.created_at).unwrap_or_default(),
+                Utils::format_rfc3339(article.updated_at.max(article.created_at))
+                    .unwrap_or_default(),
+                categories,
+                summary,
+                content,
+            ));
+        }
+
+        xml.push_str("</feed>\n");
+        xml
+    }
+
+    fn rss(config: &Config, site_root: &str, items: &[FeedItem]) -> String {
+        let site_root = Utils::escape_html(site_root);
+        let mut xml = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">
+<channel>
+    <title>{}</title>
+    <link>{}/</link>
+    <description>{}</description>
+    <atom:link href="{}/rss.xml" rel="self" type="application/rss+xml"/>
+    <lastBuildDate>{}</lastBuildDate>
+"#,
+            Utils::escape_html(&config.title),
+            site_root,
+            Utils::escape_html(&config.title),
+            site_root,
+            Utils::format_rfc2822(Self::last_updated(items)).unwrap_or_default(),
+        );
+
+        for item in items {
+            let article = item.article;
+            let categories: String = article
+                .tags
+                .iter()
+                .map(|tag| format!("        <category>{}</category>\n", Utils::escape_html(tag)))
+                .collect();
+            // rss only has the one field, so the full content wins over the summary
+            let description = item.content.as_ref().or(item.summary.as_ref());
+            let description = match description {
+                Some(text) => format!(
+                    "        <description>{}</description>\n",
+                    Utils::escape_html(text)
+                ),
+                None => String::new(),
+            };
+
+            xml.push_str(&format!(
+                r#"    <item>
+        <title>{}</title>
+        <link>{}</link>
+        <guid isPermaLink="true">{}</guid>
+        <pubDate>{}</pubDate>
+{}{}    </item>
+"#,
+                Utils::escape_html(article.title()),
+                Utils::escape_html(&item.url),
+                Utils::escape_html(&item.url),
+                Utils::format_rfc2822(article.created_at).unwrap_or_default(),
+                categories,
+                description,
+            ));
+        }
+
+        xml.push_str("</channel>\n</rss>\n");
+        xml
+    }
+
+    // the index page's article data, plus what a feed reader needs
+    fn json(config: &Config, site_root: &str, items: &[FeedItem]) -> serde_json::Result<String> {
+        let items = items
+            .iter()
+            .map(|item| {
+                let article = item.article;
+                // an item needs some content, the title is all that's left without a source
+                let content_text = match &item.content {
+                    Some(_) => None,
+                    None => Some(
+                        item.summary
+                            .clone()
+                            .unwrap_or_else(|| article.title().to_string()),
+                    ),
+                };
+                JsonFeedItem {
+                    id: item.url.clone(),
+                    url: item.url.clone(),
+                    title: article.title().to_string(),
+                    content_html: item.content.clone(),
+                    content_text,
+                    summary: item.summary.clone(),
+                    date_published: Utils::format_rfc3339(article.created_at).unwrap_or_default(),
+                    date_modified: Self::date_modified(article),
+                    tags: article.tags.clone(),
+                }
+            })
+            .collect();
+
+        let feed = JsonFeed {
+            version: "https://jsonfeed.org/version/1.1",
+            title: config.title.clone(),
+            home_page_url: format!("{}/", site_root),
+            feed_url: format!("{}/feed.json", site_root),
+            items,
+        };
+        serde_json::to_string_pretty(&feed)
+    }
+}
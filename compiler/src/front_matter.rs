@@ -0,0 +1,237 @@
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::markdown::MarkdownOverrides;
+use crate::utils::Utils;
+
+// optional metadata at the very top of an article, either
+// YAML between "---" lines, or TOML between "+++" lines
+// anything set here wins over (and is copied into) the article's entry in the info file
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FrontMatter {
+    pub title: Option<String>,       // display title, instead of the filename
+    pub tags: Option<Vec<String>>,   // replaces the tags in the info file, unless it's empty
+    pub date: Option<String>,        // "YYYY-MM-DD" or RFC 3339, replaces created_at
+    pub slug: Option<String>,        // url, instead of one made from the filename
+    pub summary: Option<String>,     // short description
+    pub draft: bool,                 // drafts are tracked, but not published
+    pub markdown: MarkdownOverrides, // CommonMark extensions, instead of the site's
+    pub toc: bool,                   // table of contents after the title
+}
+
+impl FrontMatter {
+    // split content into its front matter (default if there isn't any) and the markdown body
+    pub fn parse(content: &str) -> Result<(FrontMatter, &str), String> {
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+
+        let fence = match content.lines().next().map(|l| l.trim_end()) {
+            Some("---") => "---",
+            Some("+++") => "+++",
+            _ => return Ok((FrontMatter::default(), content)),
+        };
+
+        // byte offsets of each line, so the body can be sliced out of content
+        let mut offset = 0;
+        let mut block_start = None;
+        for line in content.split_inclusive('\n') {
+            let line_start = offset;
+            offset += line.len();
+
+            let Some(start) = block_start else {
+                block_start = Some(offset);
+                continue;
+            };
+
+            let trimmed = line.trim_end();
+            if trimmed == fence || (fence == "---" && trimmed == "...") {
+                let block = &content[start..line_start];
+                let mut front_matter = if fence == "---" {
+                    Self::parse_yaml(block)?
+                } else {
+                    Self::parse_toml(block)?
+                };
+                front_matter.validate()?;
+                // an empty list doesn't set anything, so the info file's tags (and suggest-tags) still apply
+                if front_matter
+                    .tags
+                    .as_ref()
+                    .is_some_and(|tags| tags.is_empty())
+                {
+                    front_matter.tags = None;
+                }
+                return Ok((front_matter, &content[offset..]));
+            }
+        }
+
+        Err(format!("block starting with '{}' is never closed", fence))
+    }
+
+    fn parse_yaml(block: &str) -> Result<FrontMatter, String> {
+        if block.trim().is_empty() {
+            return Ok(FrontMatter::default());
+        }
+        serde_yaml::from_str::<FrontMatter>(block).map_err(|e| format!("invalid YAML: {}", e))
+    }
+
+    fn parse_toml(block: &str) -> Result<FrontMatter, String> {
+        let mut table = block
+            .parse::<toml::Table>()
+            .map_err(|e| format!("invalid TOML: {}", e))?;
+
+        // TOML has real dates, everything else only needs them as text
+        if let Some(toml::Value::Datetime(dt)) = table.get("date") {
+            let text = dt.to_string();
+            table.insert("date".to_string(), toml::Value::String(text));
+        }
+
+        toml::Value::Table(table)
+            .try_into::<FrontMatter>()
+            .map_err(|e| format!("invalid TOML: {}", e))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some(date) = &self.date
+            && Utils::parse_date(date).is_none()
+        {
+            return Err(format!("date '{}' should look like YYYY-MM-DD", date));
+        }
+        if let Some(slug) = &self.slug
+            && Utils::format_safe_filename(slug).is_none_or(|s| s.is_empty())
+        {
+            return Err(format!("slug '{}' has no url-safe characters", slug));
+        }
+        Ok(())
+    }
+
+    // the url-safe filename this article is written to, without extension
+    // relative_path is the article's path inside the input directory, any folders are kept
+    pub fn safe_filename(&self, relative_path: &Path) -> Option<String> {
+        let name = match &self.slug {
+            Some(slug) => Utils::format_safe_filename(slug)?,
+            None => Utils::format_safe_filename(&Utils::extract_filename(relative_path)?)?,
+        };
+
+        let mut parts = Vec::new();
+        if let Some(parent) = relative_path.parent() {
+            for component in parent.components() {
+                if let std::path::Component::Normal(folder) = component {
+                    let folder = Utils::format_safe_filename(folder.to_str()?)?;
+                    if !folder.is_empty() {
+                        parts.push(folder);
+                    }
+                }
+            }
+        }
+        parts.push(name);
+
+        Some(parts.join("/"))
+    }
+
+    pub fn created_at(&self) -> Option<u64> {
+        self.date.as_deref().and_then(Utils::parse_date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> FrontMatter {
+        match FrontMatter::parse(content) {
+            Ok((front_matter, _)) => front_matter,
+            Err(message) => panic!("{:?} didn't parse: {}", content, message),
+        }
+    }
+
+    fn parse_err(content: &str) -> String {
+        match FrontMatter::parse(content) {
+            Ok(_) => panic!("{:?} should have failed", content),
+            Err(message) => message,
+        }
+    }
+
+    #[test]
+    fn yaml() {
+        let content = "---\ntitle: Hashing\ntags: [hashing, strings]\ndate: 2024-03-01\ndraft: true\n---\n# Body\n";
+        let (front_matter, body) = FrontMatter::parse(content).unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("Hashing"));
+        assert_eq!(
+            front_matter.tags,
+            Some(vec!["hashing".to_string(), "strings".to_string()])
+        );
+        assert_eq!(front_matter.date.as_deref(), Some("2024-03-01"));
+        assert!(front_matter.draft);
+        assert_eq!(body, "# Body\n");
+
+        // yaml can also end with "..."
+        let (front_matter, body) = FrontMatter::parse("---\ntitle: Dots\n...\nbody").unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("Dots"));
+        assert_eq!(body, "body");
+    }
+
+    #[test]
+    fn toml() {
+        let content =
+            "+++\ntitle = \"Hashing\"\ntags = [\"hashing\"]\ndate = 2024-03-01\n+++\n# Body\n";
+        let (front_matter, body) = FrontMatter::parse(content).unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("Hashing"));
+        assert_eq!(front_matter.tags, Some(vec!["hashing".to_string()]));
+        // a real toml date, turned into text
+        assert_eq!(front_matter.date.as_deref(), Some("2024-03-01"));
+        assert_eq!(body, "# Body\n");
+
+        // "..." only ends yaml
+        assert!(parse_err("+++\ntitle = \"x\"\n...\n").contains("never closed"));
+    }
+
+    #[test]
+    fn no_front_matter() {
+        for content in ["# Title\n\n---\n", "", "--- not a fence\n"] {
+            let (front_matter, body) = FrontMatter::parse(content).unwrap();
+            assert!(front_matter.title.is_none() && front_matter.tags.is_none());
+            assert_eq!(body, content);
+        }
+        // a byte order mark doesn't hide the fence
+        assert_eq!(
+            parse("\u{feff}---\ntitle: Marked\n---\n").title.as_deref(),
+            Some("Marked")
+        );
+    }
+
+    #[test]
+    fn unterminated_fence() {
+        assert!(parse_err("---\ntitle: Open\n# Body\n").contains("'---' is never closed"));
+        assert!(parse_err("+++\ntitle = \"Open\"\n").contains("'+++' is never closed"));
+    }
+
+    #[test]
+    fn empty_block() {
+        for content in ["---\n---\nbody", "---\n\n   \n---\nbody", "+++\n+++\nbody"] {
+            let (front_matter, body) = FrontMatter::parse(content).unwrap();
+            assert!(front_matter.title.is_none() && !front_matter.draft);
+            assert_eq!(body, "body");
+        }
+    }
+
+    #[test]
+    fn unknown_keys() {
+        assert!(parse_err("---\ntitel: Typo\n---\n").contains("titel"));
+        assert!(parse_err("+++\ntitel = \"Typo\"\n+++\n").contains("titel"));
+        assert!(parse_err("---\nmarkdown: { tabels: true }\n---\n").contains("tabels"));
+    }
+
+    #[test]
+    fn empty_tags_are_not_set() {
+        assert_eq!(parse("---\ntags: []\n---\n").tags, None);
+        assert_eq!(parse("+++\ntags = []\n+++\n").tags, None);
+    }
+
+    #[test]
+    fn validation() {
+        assert!(parse_err("---\ndate: 01/03/2024\n---\n").contains("YYYY-MM-DD"));
+        assert!(parse_err("---\nslug: \"...\"\n---\n").contains("url-safe"));
+        assert!(parse_err("---\ntitle: [unclosed\n---\n").starts_with("invalid YAML"));
+        assert!(parse_err("+++\ntitle = \n+++\n").starts_with("invalid TOML"));
+    }
+}
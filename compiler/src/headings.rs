@@ -0,0 +1,181 @@
+use pulldown_cmark::{Event, HeadingLevel, Tag, TagEnd};
+use std::collections::HashSet;
+
+use crate::utils::Utils;
+
+// where the table of contents goes, if an article wants one somewhere specific
+const TOC_MARKER: &str = "<!-- toc -->";
+
+// gives every heading an id (and a permalink to it), and builds the table of contents
+pub struct Headings {}
+
+struct Heading {
+    level: usize,
+    id: String,
+    text: String,
+}
+
+impl Headings {
+    // ids come from the heading's text, the same way filenames become urls
+    // ids set with {#id} are kept, and repeats get -1, -2, ... on the end
+    // the table of contents replaces a <!-- toc --> line, or goes after the title if toc is set
+    pub fn anchor(events: &mut Vec<Event>, toc: bool) {
+        let mut used_ids: HashSet<String> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Start(Tag::Heading { id: Some(id), .. }) => Some(id.to_string()),
+                _ => None,
+            })
+            .collect();
+
+        let mut headings = Vec::new();
+        let mut anchored = Vec::with_capacity(events.len());
+        let mut current: Option<(usize, String)> = None; // index of the start event, and the text so far
+
+        for event in events.drain(..) {
+            match event {
+                Event::Start(Tag::Heading { .. }) => {
+                    current = Some((anchored.len(), String::new()));
+                    anchored.push(event);
+                }
+                Event::Text(ref text) | Event::Code(ref text) if current.is_some() => {
+                    if let Some((_, heading_text)) = &mut current {
+                        heading_text.push_str(text);
+                    }
+                    anchored.push(event);
+                }
+                Event::End(TagEnd::Heading(level)) => {
+                    if let Some((start, text)) = current.take() {
+                        let id = Self::assign_id(&mut anchored[start], &text, &mut used_ids);
+                        anchored.push(Event::InlineHtml(
+                            format!(
+                                r##"<a class="anchor" href="#{}" aria-hidden="true">#</a>"##,
+                                Utils::escape_html(&id)
+                            )
+                            .into(),
+                        ));
+                        headings.push(Heading {
+                            level: Self::level_number(level),
+                            id,
+                            text,
+                        });
+                    }
+                    anchored.push(event);
+                }
+                event => anchored.push(event),
+            }
+        }
+        *events = anchored;
+
+        let marker = events
+            .iter()
+            .position(|event| matches!(event, Event::Html(html) if html.trim() == TOC_MARKER));
+        if marker.is_none() && !toc {
+            return;
+        }
+
+        // the title isn't part of its own table of contents
+        let title_end = match events.first() {
+            Some(Event::Start(Tag::Heading {
+                level: HeadingLevel::H1,
+                ..
+            })) => events
+                .iter()
+                .position(|event| matches!(event, Event::End(TagEnd::Heading(_)))),
+            _ => None,
+        };
+        if title_end.is_some() && !headings.is_empty() {
+            headings.remove(0);
+        }
+        let toc_html = Self::toc_html(&headings);
+
+        match marker {
+            Some(index) => events[index] = Event::Html(toc_html.into()),
+            None => {
+                let index = title_end.map(|i| i + 1).unwrap_or(0);
+                events.insert(index, Event::Html(toc_html.into()));
+            }
+        }
+    }
+
+    // sets the heading's id if it doesn't have one, returns the id either way
+    fn assign_id(start: &mut Event, text: &str, used_ids: &mut HashSet<String>) -> String {
+        let Event::Start(Tag::Heading { id, .. }) = start else {
+            return String::new();
+        };
+        if let Some(id) = id {
+            return id.to_string();
+        }
+
+        let base = Utils::format_safe_filename(text)
+            .map(|slug| slug.trim_matches(|c| c == '-' || c == '_').to_string())
+            .filter(|slug| !slug.is_empty())
+            .unwrap_or_else(|| "section".to_string());
+        let mut unique = base.clone();
+        let mut n = 1;
+        while used_ids.contains(&unique) {
+            unique = format!("{}-{}", base, n);
+            n += 1;
+        }
+
+        used_ids.insert(unique.clone());
+        *id = Some(unique.clone().into());
+        unique
+    }
+
+    fn level_number(level: HeadingLevel) -> usize {
+        match level {
+            HeadingLevel::H1 => 1,
+            HeadingLevel::H2 => 2,
+            HeadingLevel::H3 => 3,
+            HeadingLevel::H4 => 4,
+            HeadingLevel::H5 => 5,
+            HeadingLevel::H6 => 6,
+        }
+    }
+
+    // nested lists, following the heading levels
+    fn toc_html(headings: &[Heading]) -> String {
+        let Some(min_level) = headings.iter().map(|h| h.level).min() else {
+            return String::new();
+        };
+
+        let mut html = String::from(r#"<nav class="toc">"#);
+        let mut depth = 0;
+        for heading in headings {
+            let level = heading.level - min_level + 1;
+            if depth == 0 {
+                html.push_str("<ul>");
+                depth = 1;
+            } else if level > depth {
+                html.push_str("<ul>");
+                depth += 1;
+            } else {
+                html.push_str("</li>");
+                while depth > level {
+                    html.push_str("</ul></li>");
+                    depth -= 1;
+                }
+            }
+            // skipped levels, like a ### straight after a #
+            while depth < level {
+                html.push_str("<li><ul>");
+                depth += 1;
+            }
+
+            html.push_str(&format!(
+                r##"<li><a href="#{}">{}</a>"##,
+                Utils::escape_html(&heading.id),
+                Utils::escape_html(&heading.text)
+            ));
+        }
+        html.push_str("</li>");
+        while depth > 1 {
+            html.push_str("</ul></li>");
+            depth -= 1;
+        }
+        html.push_str("</ul></nav>\n");
+
+        html
+    }
+}
@@ -0,0 +1,126 @@
+use serde::Deserialize;
+use std::sync::LazyLock;
+use syntect::highlighting::ThemeSet;
+use syntect::html::{ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use crate::utils::Utils;
+
+// every class syntect emits starts with this, so they can't clash with the page's own classes
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+// loading these takes a moment, so only do it once, and only if an article has code in it
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+static THEMES: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+// syntax highlighting of fenced code blocks, done at build time
+// code is wrapped in spans with css classes, the colours come from a theme's stylesheet
+// that is only added to pages that have code on them
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HighlightOptions {
+    pub enabled: bool,
+    pub theme: String, // one of syntect's default themes, like "InspiredGitHub"
+    pub dark_theme: Option<String>, // used instead when the reader prefers a dark colour scheme
+}
+
+impl Default for HighlightOptions {
+    fn default() -> Self {
+        HighlightOptions {
+            enabled: true,
+            theme: "InspiredGitHub".to_string(),
+            dark_theme: Some("base16-ocean.dark".to_string()),
+        }
+    }
+}
+
+pub struct Highlighter {}
+
+impl Highlighter {
+    // the names of every theme that can be used, for config errors
+    pub fn theme_names() -> Vec<&'static str> {
+        THEMES.themes.keys().map(|name| name.as_str()).collect()
+    }
+
+    pub fn validate(options: &HighlightOptions) -> Result<(), String> {
+        let themes = std::iter::once(&options.theme).chain(options.dark_theme.as_ref());
+        for theme in themes {
+            if !THEMES.themes.contains_key(theme) {
+                return Err(format!(
+                    "unknown highlight theme '{}', expected one of: {}",
+                    theme,
+                    Self::theme_names().join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // a <pre> block for the code, language is the first word of the fence's info string
+    // unknown languages (or none at all) are still wrapped the same way, just not coloured in
+    pub fn highlight(code: &str, language: Option<&str>) -> String {
+        let syntax = language
+            .and_then(|lang| SYNTAXES.find_syntax_by_token(lang))
+            .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text());
+
+        let mut generator =
+            ClassedHTMLGenerator::new_with_class_style(syntax, &SYNTAXES, CLASS_STYLE);
+        let mut highlighted = true;
+        for line in LinesWithEndings::from(code) {
+            if generator
+                .parse_html_for_line_which_includes_newline(line)
+                .is_err()
+            {
+                highlighted = false;
+                break;
+            }
+        }
+        let inner = if highlighted {
+            generator.finalize()
+        } else {
+            Utils::escape_html(code)
+        };
+
+        let class = match language {
+            Some(lang) => format!(r#" class="language-{}""#, Utils::escape_html(lang)),
+            None => "".to_string(),
+        };
+        format!(
+            "<pre class=\"hl-code\"><code{}>{}</code></pre>\n",
+            class, inner
+        )
+    }
+
+    // stylesheet for the highlighted code, with the dark theme behind a media query
+    pub fn css(options: &HighlightOptions) -> String {
+        let mut css = Self::theme_css(&options.theme);
+        if let Some(dark_theme) = &options.dark_theme {
+            css.push_str(&format!(
+                "@media (prefers-color-scheme: dark) {{\n{}}}\n",
+                Self::theme_css(dark_theme)
+            ));
+        }
+        css
+    }
+
+    fn theme_css(name: &str) -> String {
+        THEMES
+            .themes
+            .get(name)
+            .and_then(|theme| {
+                syntect::html::css_for_theme_with_class_style(theme, CLASS_STYLE).ok()
+            })
+            .unwrap_or_default()
+    }
+
+    // for the config's render fingerprint
+    pub fn fingerprint(options: &HighlightOptions) -> String {
+        format!(
+            "{}:{}:{}",
+            options.enabled,
+            options.theme,
+            options.dark_theme.as_deref().unwrap_or("")
+        )
+    }
+}
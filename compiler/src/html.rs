@@ -1,27 +1,162 @@
 use std::path::Path;
 
-use crate::{OUTPUT_DIR, info::InfoWrangler};
+use crate::{
+    config::Config,
+    error::{Error, Result},
+    info::{InfoWrangler, SEARCH_FILE_NAME},
+    utils::Utils,
+};
 
 pub struct IndexHtml {}
 
+// articles per page of the index, the javascript search pages the same way
+pub const PAGE_SIZE: usize = 10;
+
 impl IndexHtml {
-    pub fn save(info_wrangler: &mut InfoWrangler) {
-        let dest_path = Path::new(OUTPUT_DIR).join("index.html");
+    // index.html is the first page, page/2.html, page/3.html, ... are the rest
+    // every page has the full list for the search, but also its own articles already in the html,
+    // so the index works (and can be crawled) without javascript
+    pub fn save(config: &Config, info_wrangler: &mut InfoWrangler) -> Result<()> {
+        info_wrangler.sort_created_at();
+        let page_count = Self::page_count(info_wrangler);
+
+        let pages_dir = config.output_dir.join("page");
+        Self::remove_stale(&pages_dir, info_wrangler, page_count)?;
+
+        for page_num in 0..page_count {
+            let dest_path = match page_num {
+                0 => config.output_dir.join("index.html"),
+                n => pages_dir.join(format!("{}.html", n + 1)),
+            };
+            let full_html = Self::format_html(config, info_wrangler, page_num, page_count)
+                .map_err(|e| Error::json("serialize public article info", &dest_path, e))?;
+            Utils::write_file(&dest_path, full_html)?;
+        }
+
+        // the filters are only needed once a search starts, so every page shares one copy
+        let search_path = config.output_dir.join(SEARCH_FILE_NAME);
+        let search_json = serde_json::to_string(&info_wrangler.get_search_file(&config.search))
+            .map_err(|e| Error::json("serialize search filters", &search_path, e))?;
+        Utils::write_file(&search_path, search_json)
+    }
 
-        std::fs::create_dir_all(dest_path.parent().unwrap())
-            .expect("Failed to create output directory");
+    // the number of pages can shrink, so delete the pages past the last one
+    // only numbered pages, articles can't be in a page folder anymore (see InfoWrangler::upsert),
+    // but pages of ones tracked from before are left alone
+    fn remove_stale(
+        pages_dir: &Path,
+        info_wrangler: &InfoWrangler,
+        page_count: usize,
+    ) -> Result<()> {
+        let entries = match std::fs::read_dir(pages_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(Error::io("read index pages", pages_dir, e)),
+        };
+        for entry in entries {
+            let path = entry
+                .map_err(|e| Error::io("read index pages", pages_dir, e))?
+                .path();
+            if !path.is_file() || path.extension().is_none_or(|ext| ext != "html") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let stale = name.parse::<usize>().is_ok_and(|n| n > page_count);
+            if !stale || info_wrangler.is_output(&format!("page/{}", name)) {
+                continue;
+            }
+            std::fs::remove_file(&path).map_err(|e| Error::io("delete index page", &path, e))?;
+        }
+        Ok(())
+    }
 
-        let full_html = Self::format_html(info_wrangler);
-        std::fs::write(&dest_path, &full_html).expect("Failed to write output file");
+    pub fn page_count(info_wrangler: &InfoWrangler) -> usize {
+        info_wrangler
+            .get_published()
+            .len()
+            .div_ceil(PAGE_SIZE)
+            .max(1)
     }
 
-    fn format_html(info_wrangler: &mut InfoWrangler) -> String {
-        info_wrangler.sort_created_at();
-        let public_info = info_wrangler.get_public_info();
-        let articles_json = serde_json::to_string(&public_info)
-            .expect("Failed to serialize articles into public JSON");
+    // url of a page of the index (counting from 0), after the base path
+    pub fn page_url(page_num: usize) -> String {
+        if page_num == 0 {
+            "/".to_string()
+        } else {
+            format!("/page/{}.html", page_num + 1)
+        }
+    }
 
-        format!(
+    fn format_html(
+        config: &Config,
+        info_wrangler: &InfoWrangler,
+        page_num: usize,
+        page_count: usize,
+    ) -> serde_json::Result<String> {
+        let mut public_info = info_wrangler.get_public_info();
+        public_info.page_num = page_num as u32;
+
+        let base_path = Utils::escape_html(&config.base_path);
+        let rows: Vec<String> = public_info
+            .articles
+            .iter()
+            .skip(page_num * PAGE_SIZE)
+            .take(PAGE_SIZE)
+            .map(|article| {
+                format!(
+                    r#"<span class="text-right">{}</span><a href="{}/{}.html">{}</a>"#,
+                    Utils::format_iso_date(article.created_at).unwrap_or_default(),
+                    base_path,
+                    article.safe_title,
+                    Utils::escape_html(&article.original_title)
+                )
+            })
+            .collect();
+        let page_href =
+            |page_num: usize| format!(r#" href="{}{}""#, base_path, Self::page_url(page_num));
+        let prev_href = match page_num {
+            0 => String::new(),
+            n => page_href(n - 1),
+        };
+        let next_href = if page_num + 1 < page_count {
+            page_href(page_num + 1)
+        } else {
+            String::new()
+        };
+
+        let articles_json = Utils::escape_script_json(&serde_json::to_string(&public_info)?);
+        let title = Utils::escape_html(&config.title);
+        let base_path_json = Utils::escape_script_json(&serde_json::to_string(&config.base_path)?);
+        let search_weights_json = serde_json::to_string(&config.search)?;
+        let source_link = match &config.source_url {
+            Some(url) => format!(
+                r#"<a href="{}" style="grid-column:2;margin-top:0.6rem;">source</a>"#,
+                Utils::escape_html(url)
+            ),
+            None => String::new(),
+        };
+
+        let tags_link = format!(
+            r#"<a href="{}/tags/" style="grid-column:2;margin-top:0.6rem;">tags</a>"#,
+            Utils::escape_html(&config.base_path)
+        );
+
+        // same condition as Feeds::save
+        let feed_links = if config.feed.enabled && config.site_root().is_some() {
+            let base_path = Utils::escape_html(&config.base_path);
+            format!(
+                r#"<link rel="alternate" type="application/atom+xml" title="{}" href="{}/feed.xml">
+    <link rel="alternate" type="application/rss+xml" title="{}" href="{}/rss.xml">
+    <link rel="alternate" type="application/feed+json" title="{}" href="{}/feed.json">"#,
+                title, base_path, title, base_path, title, base_path
+            )
+        } else {
+            String::new()
+        };
+
+        Ok(format!(
             r#"<!DOCTYPE html>
 <html lang="en">
 
@@ -29,7 +164,8 @@ impl IndexHtml {
     <link rel="icon" href="data:,">
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
     <meta charset="UTF-8">
-    <title>Bug Blog</title>
+    <title>{}</title>
+    {}
     <style>
         .text-right {{
             text-align: right;
@@ -38,13 +174,17 @@ impl IndexHtml {
     <script>
         // see PublicInfoFile in info.rs for schema
         const data = {}
+        const basePath = {}
     </script>
     <script>
-        const pageSize = 10;
+        const pageSize = {};
+        const searchWeights = {}; // see SearchOptions in search.rs
         const urlSearchParam = "search";
         const urlPageParam = "page";
         const searchLimit = 256; // max search term length
 
+        // squashText, buildTrigrams, djb2tri and bitPositions must match search.rs exactly
+
         // 1. lowercase
         // 2. replace all non-alphanumeric (excluding #) with space
         // 3. remove all spaces
@@ -74,58 +214,55 @@ impl IndexHtml {
             return Math.abs(h);
         }}
 
-        // creates a bloom filter from any set of strings (trigrams, in practice)
-        // the bloom filter is a Uint8Array of length 256 (2048 bits)
-        // uses 2 hash functions (djb2 and djb2 shifted) to set bits
-        // can store 500 elements (strings) with a false positive rate < 5%
-        const blossom = (trigrams) => {{
-            const result = new Uint8Array(256); // 2048 bits = 256 bytes
-            trigrams.forEach(trigram => {{
-                const hash = djb2tri(trigram);
-                const bitPos1 = hash % 2048;
-                const bitPos2 = (hash >> 11) % 2048;
-
-                result[Math.floor(bitPos1 / 8)] |= (1 << (bitPos1 % 8));
-                result[Math.floor(bitPos2 / 8)] |= (1 << (bitPos2 % 8));
-            }});
-            return result;
+        // k bit positions per trigram (bloom.hashes), stepping by the top bits of the hash
+        // each filter is sized by the compiler, so its bits and hashes come with it
+        const bitPositions = (hash, bloom) => {{
+            const step = Math.floor(hash / 2048) + 1;
+            const positions = [];
+            for (let i = 0; i < bloom.hashes; i++) {{
+                positions.push((hash + i * step) % bloom.bits);
+            }}
+            return positions;
         }}
 
-        // build a bloom filter for each article, then store it in article.bloom
-        const buildBlooms = () => {{
-            data.articles.forEach(article => {{
-                const allTrigrams = new Set();
-
-                const squashedTitle = squashText(article.originalTitle);
-                const titleTrigrams = buildTrigrams(squashedTitle);
-                titleTrigrams.forEach(tri => allTrigrams.add(tri));
-
-                for (tag of article.tags) {{
-                    const squashedTag = squashText(tag);
-                    const tagTrigrams = buildTrigrams(squashedTag);
-                    tagTrigrams.forEach(tri => allTrigrams.add(tri));
-                }}
-
-                article.bloom = blossom(allTrigrams);
-            }});
+        // each article's bloom filters are built by the compiler (search.rs) into search.json,
+        // fetch and decode them once, the first time they're needed
+        // an article without a filter (or all of them, if the fetch fails) gets an empty one, that matches nothing
+        let bloomsLoaded = null;
+        const loadBlooms = () => {{
+            if (bloomsLoaded) return bloomsLoaded;
+            const empty = () => ({{ bits: 0, hashes: 0, filter: new Uint8Array(0) }});
+            const decode = (bloom) => {{
+                if (!bloom) return empty();
+                bloom.filter = Uint8Array.from(atob(bloom.filter), c => c.charCodeAt(0));
+                return bloom;
+            }};
+            bloomsLoaded = fetch(`${{basePath}}/search.json`)
+                .then(response => response.ok ? response.json() : {{ articles: {{}} }})
+                .catch(() => ({{ articles: {{}} }}))
+                .then(search => {{
+                    data.articles.forEach(article => {{
+                        const entry = search.articles[article.safeTitle] || {{}};
+                        article.titleBloom = decode(entry.titleBloom);
+                        article.tagBloom = decode(entry.tagBloom);
+                        article.bodyBloom = decode(entry.bodyBloom);
+                    }});
+                }});
+            return bloomsLoaded;
         }}
 
-        // count the number of matching bits between two bloom filters
-        // not strictly kosher bloom filter usage, but works for our purposes
-        const compareBlooms = (needle, haystack) => {{
-            let score = 0;
-            for (let i = 0; i < needle.length; i++) {{
-                const nByte = needle[i];
-                const hByte = haystack[i];
-                const commonBits = nByte & hByte;
-                // count set bits in commonBits
-                for (let b = 0; b < 8; b++) {{
-                    if (commonBits & (1 << b)) {{
-                        score++;
-                    }}
+        // count how many of the hashed trigrams are (probably) in a bloom filter
+        const countMatches = (hashes, bloom) => {{
+            if (bloom.bits === 0) return 0;
+            let count = 0;
+            hashes.forEach(hash => {{
+                const found = bitPositions(hash, bloom).every(bitPos =>
+                    bloom.filter[Math.floor(bitPos / 8)] & (1 << (bitPos % 8)));
+                if (found) {{
+                    count++;
                 }}
-            }}
-            return score;
+            }});
+            return count;
         }}
 
         // update the ui to display only result articles
@@ -141,7 +278,7 @@ impl IndexHtml {
                 articlesDiv.appendChild(dateSpan);
 
                 const titleLink = document.createElement("a");
-                titleLink.href = `/blag/${{article.safeTitle}}.html`;
+                titleLink.href = `${{basePath}}/${{article.safeTitle}}.html`;
                 titleLink.textContent = article.originalTitle;
                 articlesDiv.appendChild(titleLink);
             }});
@@ -158,14 +295,28 @@ impl IndexHtml {
                 return;
             }}
 
+            // scores from whatever the search is by the time the filters arrive
+            loadBlooms().then(scoreResults);
+        }}
+
+        // rank the articles by how well their filters match searchTerm, for updateResults
+        const scoreResults = () => {{
+            if (data.searchTerm.length < 3) return;
             const squashedSearch = squashText(data.searchTerm);
-            const searchTrigrams = buildTrigrams(squashedSearch);
-            const searchBloom = blossom(searchTrigrams);
+            const searchHashes = [...buildTrigrams(squashedSearch)].map(djb2tri);
 
             let articleScores = [];
             for (let i = 0; i < data.articles.length; i++) {{
                 const article = data.articles[i];
-                const score = compareBlooms(searchBloom, article.bloom);
+                // a body has so many trigrams that a few false positives are almost certain,
+                // so it only counts once it has at least half of the search's trigrams
+                let bodyMatches = countMatches(searchHashes, article.bodyBloom);
+                if (bodyMatches * 2 < searchHashes.length) {{
+                    bodyMatches = 0;
+                }}
+                const score = searchWeights.title_weight * countMatches(searchHashes, article.titleBloom)
+                    + searchWeights.tag_weight * countMatches(searchHashes, article.tagBloom)
+                    + searchWeights.body_weight * bodyMatches;
                 if (score > 0) {{
                     articleScores.push({{ index: i, score: score }});
                 }}
@@ -201,19 +352,21 @@ impl IndexHtml {
             window.history.replaceState({{}}, '', `${{window.location.pathname}}?${{params.toString()}}`);
         }};
 
-        // handle click on 'prev' button
+        // handle click on 'prev' link, returns false so the static page isn't loaded instead
         const handlePrevClick = () => {{
-            if (data.pageNum <= 0) return;
+            if (data.pageNum <= 0) return false;
             data.pageNum = data.pageNum - 1;
             updateUrlParam(urlPageParam, data.pageNum);
             updateResults();
+            return false;
         }};
 
-        // handle click on 'next' button
+        // handle click on 'next' link
         const handleNextClick = () => {{
             data.pageNum = data.pageNum + 1;
             updateUrlParam(urlPageParam, data.pageNum);
             updateResults();
+            return false;
         }};
 
         // read pageNum and searchTerm from URL params on load
@@ -241,9 +394,6 @@ impl IndexHtml {
             // display results based on initial params
             updateResults();
         }};
-
-        // build bloom filters for all articles
-        buildBlooms();
     </script>
 </head>
 
@@ -252,22 +402,36 @@ impl IndexHtml {
         <!-- cheeky lil spacer to align dates -->
         <span style="visibility:hidden;">2020-01-01-</span>
         <div style="margin-bottom:1rem;">
-            <h1 style="margin:0;">Bug Blog</h1>
+            <h1 style="margin:0;">{}</h1>
             <input id="search-bar" type="text" name="search" placeholder="search" oninput="handleSearchInput(event)">
         </div>
         <div id="articles" style="display:grid;grid-template-columns:subgrid;grid-column:1/-1;row-gap:0.4rem;">
+            {}
         </div>
         <div style="grid-column:2;margin-top:0.8rem;display:flex;gap:1rem;align-items:center;">
-            <button id="prev" onclick="handlePrevClick()">prev</button>
-            <span id="page-num">1</span>
-            <button id="next" onclick="handleNextClick()">next</button>
+            <a id="prev"{} onclick="return handlePrevClick()" style="cursor:pointer;">prev</a>
+            <span id="page-num">{}</span>
+            <a id="next"{} onclick="return handleNextClick()" style="cursor:pointer;">next</a>
         </div>
-        <a href="https://github.com/cruncha-cruncha/blag" style="grid-column:2;margin-top:0.6rem;">source</a>
+        {}
+        {}
     </div>
 </body>
 
 </html>"#,
-            articles_json
-        )
+            title,
+            feed_links,
+            articles_json,
+            base_path_json,
+            PAGE_SIZE,
+            search_weights_json,
+            title,
+            rows.join("\n            "),
+            prev_href,
+            page_num + 1,
+            next_href,
+            tags_link,
+            source_link
+        ))
     }
 }
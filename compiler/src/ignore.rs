@@ -0,0 +1,167 @@
+use std::path::Path;
+
+use crate::error::{Error, Result};
+
+// a small subset of .gitignore, read from the ignore file at the top of the input directory
+// - blank lines and lines starting with # are skipped
+// - a trailing / only matches directories
+// - patterns without a / match a file or folder name anywhere
+// - patterns with a / match the whole path, relative to the input directory
+// - * matches anything except /, ** matches anything, ? matches one character
+pub struct IgnoreRules {
+    patterns: Vec<Pattern>,
+}
+
+struct Pattern {
+    glob: String,
+    dir_only: bool,
+    anchored: bool, // match against the whole relative path, not just the name
+}
+
+impl IgnoreRules {
+    pub fn load(input_dir: &Path, file_name: &str) -> Result<Self> {
+        let path = input_dir.join(file_name);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(Error::io("read ignore file", &path, e)),
+        };
+        Ok(Self::parse(&content))
+    }
+
+    fn parse(content: &str) -> Self {
+        let patterns = content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let dir_only = line.ends_with('/');
+                let line = line.trim_end_matches('/');
+                let anchored = line.contains('/');
+                Pattern {
+                    glob: line.trim_start_matches('/').to_string(),
+                    dir_only,
+                    anchored,
+                }
+            })
+            .collect();
+
+        IgnoreRules { patterns }
+    }
+
+    // relative_path is relative to the input directory, with / separators
+    pub fn is_ignored(&self, relative_path: &str, is_dir: bool) -> bool {
+        let name = relative_path.rsplit('/').next().unwrap_or(relative_path);
+        self.patterns.iter().any(|pattern| {
+            if pattern.dir_only && !is_dir {
+                return false;
+            }
+            let text = if pattern.anchored {
+                relative_path
+            } else {
+                name
+            };
+            Self::glob_match(pattern.glob.as_bytes(), text.as_bytes())
+        })
+    }
+
+    fn glob_match(glob: &[u8], text: &[u8]) -> bool {
+        match glob.first() {
+            None => text.is_empty(),
+            Some(b'*') if glob.get(1) == Some(&b'*') => {
+                // "**/" can also match nothing at all
+                let rest = &glob[2..];
+                if let Some(rest_no_slash) = rest.strip_prefix(b"/")
+                    && Self::glob_match(rest_no_slash, text)
+                {
+                    return true;
+                }
+                (0..=text.len()).any(|i| Self::glob_match(rest, &text[i..]))
+            }
+            Some(b'*') => {
+                let rest = &glob[1..];
+                for i in 0..=text.len() {
+                    if Self::glob_match(rest, &text[i..]) {
+                        return true;
+                    }
+                    if text.get(i) == Some(&b'/') {
+                        break;
+                    }
+                }
+                false
+            }
+            Some(b'?') => {
+                !text.is_empty() && text[0] != b'/' && Self::glob_match(&glob[1..], &text[1..])
+            }
+            Some(c) => text.first() == Some(c) && Self::glob_match(&glob[1..], &text[1..]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob_match(glob: &str, text: &str) -> bool {
+        IgnoreRules::glob_match(glob.as_bytes(), text.as_bytes())
+    }
+
+    #[test]
+    fn star() {
+        assert!(glob_match("*.md", "notes.md"));
+        assert!(glob_match("*.md", ".md"));
+        assert!(glob_match("draft*", "draft"));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(!glob_match("*.md", "notes.markdown"));
+        // never across a /
+        assert!(!glob_match("*.md", "drafts/notes.md"));
+        assert!(glob_match("drafts/*.md", "drafts/notes.md"));
+        assert!(!glob_match("drafts/*.md", "drafts/old/notes.md"));
+    }
+
+    #[test]
+    fn double_star() {
+        assert!(glob_match("drafts/**", "drafts/old/notes.md"));
+        assert!(glob_match("**/notes.md", "a/b/notes.md"));
+        // "**/" can match nothing
+        assert!(glob_match("**/notes.md", "notes.md"));
+        assert!(glob_match("a/**/b.md", "a/b.md"));
+        assert!(glob_match("a/**/b.md", "a/x/y/b.md"));
+        assert!(!glob_match("a/**/b.md", "b/x/b.md"));
+    }
+
+    #[test]
+    fn question_mark() {
+        assert!(glob_match("v?.md", "v1.md"));
+        assert!(!glob_match("v?.md", "v.md"));
+        assert!(!glob_match("v?.md", "v12.md"));
+        assert!(!glob_match("a?b", "a/b"));
+    }
+
+    #[test]
+    fn anchored_and_unanchored() {
+        let rules = IgnoreRules::parse("# comment\n\nscratch.md\n/top.md\nold/notes.md\n");
+        // no / matches the name anywhere
+        assert!(rules.is_ignored("scratch.md", false));
+        assert!(rules.is_ignored("deep/down/scratch.md", false));
+        // a leading / anchors to the input directory
+        assert!(rules.is_ignored("top.md", false));
+        assert!(!rules.is_ignored("sub/top.md", false));
+        // so does a / in the middle
+        assert!(rules.is_ignored("old/notes.md", false));
+        assert!(!rules.is_ignored("x/old/notes.md", false));
+        // comments and blank lines aren't patterns
+        assert!(!rules.is_ignored("# comment", false));
+        assert!(!rules.is_ignored("", false));
+    }
+
+    #[test]
+    fn directory_patterns() {
+        let rules = IgnoreRules::parse("drafts/\n/private/\n");
+        assert!(rules.is_ignored("drafts", true));
+        assert!(rules.is_ignored("nested/drafts", true));
+        assert!(!rules.is_ignored("drafts", false));
+        assert!(rules.is_ignored("private", true));
+        assert!(!rules.is_ignored("nested/private", true));
+    }
+}
@@ -1,17 +1,34 @@
-use crate::INFO_FILE_NAME;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::front_matter::FrontMatter;
+use crate::search::{Bloom, Search, SearchOptions};
+use crate::sketch::{RENAME_SIMILARITY, Sketch};
 use crate::utils::Utils;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 pub struct InfoWrangler {
+    info_file_path: PathBuf,
+    input_dir: PathBuf, // article paths are made relative to this, for their urls
     info_file: InfoFile,
     lookup: HashMap<String, usize>, // maps safe_filename to index in info_files.articles
+    added: HashSet<String>,         // safe_filenames of articles first seen this run
+    paths: HashMap<PathBuf, String>, // maps source path to safe_filename, for everything upserted
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 struct InfoFile {
     articles: Vec<ArticleInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    redirects: Vec<Redirect>,
+}
+
+// an article that used to live at from.html and now lives at to.html
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Redirect {
+    pub from: String, // old safe_filename
+    pub to: String,   // current safe_filename
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -22,6 +39,39 @@ pub struct ArticleInfo {
     pub updated_at: u64,           // unix timestamp in seconds
     pub content_hash: String,      // base64-encoded sha256
     pub tags: Vec<String>,         // bloom filter of tags, maybe base64 encoded? or bigint?
+    #[serde(default)]
+    pub content_sketch: String, // base64-encoded minhash, see sketch.rs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>, // from front matter, replaces original_filename for display
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>, // from front matter
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub draft: bool, // from front matter, drafts aren't rendered or listed
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub render_hash: String, // base64-encoded sha256 of everything the last render depended on
+    #[serde(skip)]
+    pub body_filter: Option<Bloom>, // bloom filter of the body's text, cached in search.json
+    #[serde(skip)]
+    pub body_html: Option<String>, // the article's own html, if it was rendered this run, for the feeds
+}
+
+impl ArticleInfo {
+    pub fn title(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.original_filename)
+    }
+
+    // copy everything the author set in the article itself over what's in the info file
+    fn apply_front_matter(&mut self, front_matter: &FrontMatter) {
+        self.title = front_matter.title.clone();
+        self.summary = front_matter.summary.clone();
+        self.draft = front_matter.draft;
+        if let Some(tags) = &front_matter.tags {
+            self.tags = tags.clone();
+        }
+        if let Some(created_at) = front_matter.created_at() {
+            self.created_at = created_at;
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -42,12 +92,35 @@ pub struct PublicArticleInfo {
     pub tags: Vec<String>,
 }
 
+// search.json, the search's bloom filters of every published article, keyed by safe_filename
+// the index pages fetch it once a search starts, and the build reads the body filters back,
+// they only change with the content
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SearchFile {
+    pub articles: BTreeMap<String, SearchEntry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchEntry {
+    pub content_hash: String, // of the content the body filter was built from
+    pub title_bloom: Bloom,   // see search.rs
+    pub tag_bloom: Bloom,
+    pub body_bloom: Option<Bloom>, // only once the article's been processed
+}
+
+pub const SEARCH_FILE_NAME: &str = "search.json";
+
+// folders of the output directory the site's own pages are written to, articles can't go there
+const RESERVED_FOLDERS: &[&str] = &["page", "tags"];
+
 impl PublicInfoFile {
     fn from(info_file: &InfoFile) -> Self {
         let articles = info_file
             .articles
             .iter()
-            .map(|article| PublicArticleInfo::from(article))
+            .filter(|article| !article.draft)
+            .map(PublicArticleInfo::from)
             .collect();
 
         PublicInfoFile {
@@ -60,9 +133,9 @@ impl PublicInfoFile {
 }
 
 impl PublicArticleInfo {
-    fn from(article: &ArticleInfo) -> Self {
+    pub fn from(article: &ArticleInfo) -> Self {
         PublicArticleInfo {
-            original_title: article.original_filename.clone(),
+            original_title: article.title().to_string(),
             safe_title: article.safe_filename.clone(),
             created_at: article.created_at,
             tags: article.tags.clone(),
@@ -70,68 +143,113 @@ impl PublicArticleInfo {
     }
 }
 
+impl SearchFile {
+    fn from(info_file: &InfoFile, options: &SearchOptions) -> Self {
+        let articles = info_file
+            .articles
+            .iter()
+            .filter(|article| !article.draft)
+            .map(|article| {
+                let entry = SearchEntry {
+                    content_hash: article.content_hash.clone(),
+                    title_bloom: Search::title_bloom(options, article.title()),
+                    tag_bloom: Search::tag_bloom(options, &article.tags),
+                    body_bloom: article.body_filter.clone(),
+                };
+                (article.safe_filename.clone(), entry)
+            })
+            .collect();
+        SearchFile { articles }
+    }
+
+    // the last build's, nothing if there isn't one (or it can't be read, it's written again anyway)
+    fn load(path: &Path) -> Self {
+        std::fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+}
+
 impl InfoFile {
     fn new() -> Self {
-        InfoFile { articles: vec![] }
+        InfoFile {
+            articles: vec![],
+            redirects: vec![],
+        }
     }
 }
 
+// an article's chronological neighbours, for the previous / next links
+pub struct Neighbours<'a> {
+    pub older: Option<&'a ArticleInfo>,
+    pub newer: Option<&'a ArticleInfo>,
+}
+
 impl InfoWrangler {
-    fn new() -> Self {
+    fn new(info_file_path: PathBuf, input_dir: PathBuf) -> Self {
         InfoWrangler {
+            info_file_path,
+            input_dir,
             info_file: InfoFile::new(),
             lookup: HashMap::new(),
+            added: HashSet::new(),
+            paths: HashMap::new(),
         }
     }
 
-    pub fn init() -> Self {
-        let info_file_path = Path::new(".").join(INFO_FILE_NAME);
+    pub fn init(config: &Config) -> Result<Self> {
+        let info_file_path = config.info_file.clone();
         let file = match std::fs::File::open(&info_file_path) {
             Ok(f) => f,
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                return InfoWrangler::new();
-            }
-            Err(e) => {
-                panic!(
-                    "Failed to open tracking info file ({:?}) : {}",
-                    info_file_path, e
-                );
+                return Ok(InfoWrangler::new(info_file_path, config.input_dir.clone()));
             }
+            Err(e) => return Err(Error::io("open tracking info file", &info_file_path, e)),
         };
 
         let mut reader = std::io::BufReader::new(file);
-        let info_file = match serde_json::from_reader::<_, InfoFile>(&mut reader) {
-            Ok(v) => v,
-            Err(e) => {
-                panic!(
-                    "Failed to deserialize tracking info (file {:?}) from JSON: {}",
-                    info_file_path, e
-                );
+        let mut info_file = serde_json::from_reader::<_, InfoFile>(&mut reader)
+            .map_err(|e| Error::json("deserialize tracking info", &info_file_path, e))?;
+
+        // body filters of articles that haven't changed since search.json was written
+        let mut search_file = SearchFile::load(&config.output_dir.join(SEARCH_FILE_NAME));
+        for article in info_file.articles.iter_mut() {
+            if let Some(entry) = search_file.articles.remove(&article.safe_filename)
+                && entry.content_hash == article.content_hash
+            {
+                article.body_filter = entry.body_bloom;
             }
-        };
+        }
 
-        InfoWrangler {
+        Ok(InfoWrangler {
+            info_file_path,
+            input_dir: config.input_dir.clone(),
             info_file,
             lookup: HashMap::new(),
-        }
+            added: HashSet::new(),
+            paths: HashMap::new(),
+        })
     }
 
     pub fn get_public_info(&self) -> PublicInfoFile {
         PublicInfoFile::from(&self.info_file)
     }
 
-    pub fn save(&mut self) {
+    pub fn get_search_file(&self, options: &SearchOptions) -> SearchFile {
+        SearchFile::from(&self.info_file, options)
+    }
+
+    pub fn save(&mut self) -> Result<()> {
         // save to regular location
         self.sort_alphabetical();
-        let info_file_path = Path::new(".").join(INFO_FILE_NAME);
-        let file = std::fs::File::create(&info_file_path).expect(&format!(
-            "Failed to create tracking info file ({:?})",
-            info_file_path
-        ));
+        let info_file_path = &self.info_file_path;
+        let file = std::fs::File::create(info_file_path)
+            .map_err(|e| Error::io("create tracking info file", info_file_path, e))?;
 
         let mut writer = std::io::BufWriter::new(file);
         serde_json::to_writer_pretty(&mut writer, &self.info_file)
-            .expect("Failed to serialize tracking info to JSON");
+            .map_err(|e| Error::json("serialize tracking info", info_file_path, e))?;
 
         // // save to output directory
         // self.sort_created_at();
@@ -148,91 +266,380 @@ impl InfoWrangler {
         // let mut writer = std::io::BufWriter::new(file);
         // serde_json::to_writer(&mut writer, &small_info)
         //     .expect("Failed to serialize tracking info to JSON");
+
+        Ok(())
     }
 
-    pub fn upsert(&mut self, path: &Path) {
+    pub fn upsert(&mut self, path: &Path, front_matter: &FrontMatter) -> Result<()> {
         let original_filename = match Utils::extract_filename(path) {
             Some(name) => name,
-            None => return,
+            None => return Ok(()),
         };
 
-        let safe_filename = match Utils::format_safe_filename(&original_filename) {
+        let relative_path = path.strip_prefix(&self.input_dir).unwrap_or(path);
+        let safe_filename = match front_matter.safe_filename(relative_path) {
             Some(name) => name,
-            None => return,
+            None => return Ok(()),
         };
 
-        for (i, article) in self.info_file.articles.iter().enumerate() {
+        if RESERVED_FOLDERS
+            .iter()
+            .any(|folder| safe_filename.starts_with(&format!("{}/", folder)))
+        {
+            return Err(Error::ReservedSafeFilename {
+                safe_filename,
+                path: path.to_path_buf(),
+            });
+        }
+
+        for (i, article) in self.info_file.articles.iter_mut().enumerate() {
             if article.safe_filename == safe_filename {
                 if self.lookup.insert(safe_filename.clone(), i).is_some() {
-                    panic!("Duplicate safe filename detected: {}", safe_filename);
+                    return Err(Error::DuplicateSafeFilename {
+                        safe_filename,
+                        path: path.to_path_buf(),
+                    });
                 }
-                return;
+                article.original_filename = original_filename;
+                article.apply_front_matter(front_matter);
+                self.paths.insert(path.to_path_buf(), safe_filename);
+                return Ok(());
             }
         }
 
+        if self.lookup.contains_key(&safe_filename) {
+            return Err(Error::DuplicateSafeFilename {
+                safe_filename,
+                path: path.to_path_buf(),
+            });
+        }
+
         let now = match Utils::get_timestamp() {
             Some(ts) => ts,
-            None => return,
+            None => return Ok(()),
         };
 
-        let new_article = ArticleInfo {
-            original_filename: original_filename,
+        let mut new_article = ArticleInfo {
+            original_filename,
             safe_filename: safe_filename.clone(),
             created_at: now,
             updated_at: now,
             content_hash: String::new(),
             tags: vec![],
+            content_sketch: String::new(),
+            title: None,
+            summary: None,
+            draft: false,
+            render_hash: String::new(),
+            body_filter: None,
+            body_html: None,
         };
+        new_article.apply_front_matter(front_matter);
+
+        // a new article takes over the url, so it can't also be a redirect
+        self.info_file
+            .redirects
+            .retain(|redirect| redirect.from != safe_filename);
 
+        self.added.insert(safe_filename.clone());
+        self.paths.insert(path.to_path_buf(), safe_filename.clone());
         self.info_file.articles.push(new_article);
         self.lookup
             .insert(safe_filename, self.info_file.articles.len() - 1);
+        Ok(())
     }
 
     pub fn update_content(&mut self, path: &Path, content: &str) {
-        let original_filename = match Utils::extract_filename(path) {
-            Some(name) => name,
+        let index = match self.paths.get(path).and_then(|name| self.lookup.get(name)) {
+            Some(idx) => idx,
             None => return,
         };
 
-        let safe_filename = match Utils::format_safe_filename(&original_filename) {
-            Some(name) => name,
+        let content_hash = Utils::hash_base64(content.as_bytes());
+
+        if let Some(article) = self.info_file.articles.get_mut(*index) {
+            if article.content_hash != content_hash {
+                article.content_hash = content_hash;
+                article.updated_at = Utils::get_timestamp().unwrap_or(article.updated_at);
+                article.content_sketch = Sketch::compute(content);
+                article.body_filter = None; // out of date, Articles::process builds it again
+            } else if article.content_sketch.is_empty() {
+                // backfill for info files written before sketches existed
+                article.content_sketch = Sketch::compute(content);
+            }
+        }
+    }
+
+    pub fn set_render_hash(&mut self, path: &Path, render_hash: String) {
+        let index = match self.paths.get(path).and_then(|name| self.lookup.get(name)) {
+            Some(idx) => *idx,
             None => return,
         };
+        if let Some(article) = self.info_file.articles.get_mut(index) {
+            article.render_hash = render_hash;
+        }
+    }
 
-        let index = match self.lookup.get(&safe_filename) {
-            Some(idx) => idx,
+    pub fn set_tags(&mut self, path: &Path, tags: Vec<String>) {
+        let index = match self.paths.get(path).and_then(|name| self.lookup.get(name)) {
+            Some(idx) => *idx,
+            None => return,
+        };
+        if let Some(article) = self.info_file.articles.get_mut(index) {
+            article.tags = tags;
+        }
+    }
+
+    pub fn set_body_html(&mut self, path: &Path, body_html: String) {
+        let index = match self.paths.get(path).and_then(|name| self.lookup.get(name)) {
+            Some(idx) => *idx,
+            None => return,
+        };
+        if let Some(article) = self.info_file.articles.get_mut(index) {
+            article.body_html = Some(body_html);
+        }
+    }
+
+    pub fn set_body_filter(&mut self, path: &Path, body_filter: Bloom) {
+        let index = match self.paths.get(path).and_then(|name| self.lookup.get(name)) {
+            Some(idx) => *idx,
             None => return,
         };
+        if let Some(article) = self.info_file.articles.get_mut(index) {
+            article.body_filter = Some(body_filter);
+        }
+    }
 
-        let bytes = <sha2::Sha256 as sha2::Digest>::digest(content.as_bytes());
-        let content_hash =
-            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, bytes);
+    // an article that's new this run, with the same or nearly the same content as a stale article,
+    // is assumed to be that stale article renamed: it inherits created_at, updated_at and tags,
+    // the stale entry is removed, and a redirect is recorded from the old url to the new one
+    // must be called after every existing article has been upserted, and before pruning
+    pub fn detect_renames(&mut self, paths: &[PathBuf]) -> Vec<Redirect> {
+        let mut renames = Vec::new();
+
+        for path in paths {
+            let safe_filename = match self.paths.get(path) {
+                Some(name) => name.clone(),
+                None => continue,
+            };
+
+            if !self.added.contains(&safe_filename) {
+                continue;
+            }
 
-        self.info_file.articles.get_mut(*index).map(|article| {
-            if article.content_hash != content_hash {
-                article.content_hash = content_hash;
-                article.updated_at = Utils::get_timestamp().unwrap_or(article.updated_at);
+            // unreadable files are reported when rendering, not here
+            let content = match std::fs::read_to_string(path) {
+                Ok(c) => c,
+                Err(_) => continue,
+            };
+
+            let old_index = match self.find_renamed_from(&content) {
+                Some(i) => i,
+                None => continue,
+            };
+
+            let old = self.info_file.articles.remove(old_index);
+            self.rebuild_lookup();
+
+            if let Some(index) = self.lookup.get(&safe_filename)
+                && let Some(article) = self.info_file.articles.get_mut(*index)
+            {
+                // front matter in the renamed file still wins
+                let front_matter = FrontMatter::parse(&content)
+                    .map(|(fm, _)| fm)
+                    .unwrap_or_default();
+                if front_matter.date.is_none() {
+                    article.created_at = old.created_at;
+                }
+                if front_matter.tags.is_none() {
+                    article.tags = old.tags;
+                }
+                article.updated_at = old.updated_at;
+                // carry the old hash over, so update_content bumps updated_at only if the content changed
+                article.content_hash = old.content_hash;
+                article.content_sketch = old.content_sketch;
+                article.body_filter = old.body_filter;
             }
-        });
+            self.added.remove(&safe_filename);
+
+            // anything that pointed at the old url should now point at the new one
+            for redirect in self.info_file.redirects.iter_mut() {
+                if redirect.to == old.safe_filename {
+                    redirect.to = safe_filename.clone();
+                }
+            }
+            let redirect = Redirect {
+                from: old.safe_filename,
+                to: safe_filename,
+            };
+            self.info_file.redirects.push(redirect.clone());
+            renames.push(redirect);
+        }
+
+        renames
+    }
+
+    // index of the stale article that content most likely used to be
+    // an identical content_hash wins, otherwise the most similar sketch above the threshold
+    fn find_renamed_from(&self, content: &str) -> Option<usize> {
+        let content_hash = Utils::hash_base64(content.as_bytes());
+
+        let is_stale = |article: &ArticleInfo| !self.lookup.contains_key(&article.safe_filename);
+
+        let exact = self
+            .info_file
+            .articles
+            .iter()
+            .position(|article| is_stale(article) && article.content_hash == content_hash);
+        if exact.is_some() {
+            return exact;
+        }
+
+        let sketch = Sketch::compute(content);
+        let mut best: Option<(usize, f64)> = None;
+        for (i, article) in self.info_file.articles.iter().enumerate() {
+            if !is_stale(article) {
+                continue;
+            }
+            let similarity = match Sketch::similarity(&sketch, &article.content_sketch) {
+                Some(s) if s >= RENAME_SIMILARITY => s,
+                _ => continue,
+            };
+            if best.is_none_or(|(_, s)| similarity > s) {
+                best = Some((i, similarity));
+            }
+        }
+
+        best.map(|(i, _)| i)
+    }
+
+    pub fn get_redirects(&self) -> &[Redirect] {
+        &self.info_file.redirects
     }
 
     pub fn get_article(&self, path: &Path) -> Option<&ArticleInfo> {
-        let original_filename = Utils::extract_filename(path)?;
-        let safe_filename = Utils::format_safe_filename(&original_filename)?;
-        let index = self.lookup.get(&safe_filename)?;
+        let safe_filename = self.paths.get(path)?;
+        let index = self.lookup.get(safe_filename)?;
         self.info_file.articles.get(*index)
     }
 
-    pub fn sort_created_at(&mut self) {
+    // whether an article (stale ones too) or a redirect stub is written to <safe_filename>.html
+    pub fn is_output(&self, safe_filename: &str) -> bool {
         self.info_file
             .articles
-            .sort_by(|a, b| b.created_at.cmp(&a.created_at));
+            .iter()
+            .any(|article| article.safe_filename == safe_filename)
+            || self
+                .info_file
+                .redirects
+                .iter()
+                .any(|redirect| redirect.from == safe_filename)
+    }
+
+    // the published articles just before (older) and after (newer) this one
+    // follows the order of the articles, so sort_created_at first
+    pub fn get_neighbours(&self, path: &Path) -> Neighbours<'_> {
+        let none = Neighbours {
+            older: None,
+            newer: None,
+        };
+        let Some(safe_filename) = self.paths.get(path) else {
+            return none;
+        };
+        let published: Vec<&ArticleInfo> = self
+            .info_file
+            .articles
+            .iter()
+            .filter(|article| !article.draft && self.lookup.contains_key(&article.safe_filename))
+            .collect();
+        let Some(i) = published
+            .iter()
+            .position(|article| &article.safe_filename == safe_filename)
+        else {
+            return none;
+        };
+
+        Neighbours {
+            older: published.get(i + 1).copied(),
+            newer: i.checked_sub(1).and_then(|i| published.get(i)).copied(),
+        }
+    }
+
+    // everything listed on the index page, in the current order
+    pub fn get_published(&self) -> Vec<&ArticleInfo> {
+        self.info_file
+            .articles
+            .iter()
+            .filter(|article| !article.draft)
+            .collect()
+    }
+
+    // the source file of an article, if it was upserted this run
+    pub fn get_path(&self, safe_filename: &str) -> Option<&Path> {
+        self.paths
+            .iter()
+            .find(|(_, name)| name.as_str() == safe_filename)
+            .map(|(path, _)| path.as_path())
+    }
+
+    // articles in the info file that weren't upserted this run, i.e. their source file is gone
+    pub fn get_stale(&self) -> Vec<&ArticleInfo> {
+        self.info_file
+            .articles
+            .iter()
+            .filter(|article| !self.lookup.contains_key(&article.safe_filename))
+            .collect()
+    }
+
+    // remove stale articles (see get_stale), returning what was removed,
+    // along with the redirects that pointed at them
+    // must be called after every existing article has been upserted
+    pub fn prune(&mut self) -> (Vec<ArticleInfo>, Vec<Redirect>) {
+        let (keep, stale): (Vec<ArticleInfo>, Vec<ArticleInfo>) = self
+            .info_file
+            .articles
+            .drain(..)
+            .partition(|article| self.lookup.contains_key(&article.safe_filename));
+
+        self.info_file.articles = keep;
+        self.rebuild_lookup();
+
+        // redirects to a removed article would only lead to a 404
+        let removed: HashSet<&str> = stale.iter().map(|a| a.safe_filename.as_str()).collect();
+        let (redirects, dropped): (Vec<Redirect>, Vec<Redirect>) = self
+            .info_file
+            .redirects
+            .drain(..)
+            .partition(|redirect| !removed.contains(redirect.to.as_str()));
+        self.info_file.redirects = redirects;
+
+        (stale, dropped)
+    }
+
+    // newest first, articles from the same moment are in url order so the order is always the same
+    pub fn sort_created_at(&mut self) {
+        self.info_file.articles.sort_by(|a, b| {
+            b.created_at
+                .cmp(&a.created_at)
+                .then_with(|| a.safe_filename.cmp(&b.safe_filename))
+        });
+        self.rebuild_lookup();
     }
 
     pub fn sort_alphabetical(&mut self) {
         self.info_file
             .articles
             .sort_by(|a, b| a.original_filename.cmp(&b.original_filename));
+        self.rebuild_lookup();
+    }
+
+    // indexes shift whenever articles are sorted or removed
+    // only articles that were already in the lookup are kept in it
+    fn rebuild_lookup(&mut self) {
+        for (i, article) in self.info_file.articles.iter().enumerate() {
+            if let Some(index) = self.lookup.get_mut(&article.safe_filename) {
+                *index = i;
+            }
+        }
     }
 }
@@ -0,0 +1,23 @@
+// everything but the command line entry point, shared by the blag-compiler and extra-fuzzy binaries
+mod articles;
+pub mod cli;
+pub mod commands;
+pub mod config;
+mod error;
+mod feed;
+mod front_matter;
+mod headings;
+mod highlight;
+mod html;
+mod ignore;
+mod info;
+mod markdown;
+mod math;
+pub mod search;
+pub mod serve;
+mod sitemap;
+mod sketch;
+mod tags;
+mod tfidf;
+mod utils;
+mod watch;
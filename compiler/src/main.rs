@@ -1,58 +1,48 @@
-mod articles;
-mod html;
-mod info;
-mod utils;
-
-const INFO_FILE_NAME: &str = "articles.json";
-const INPUT_DIR: &str = "../articles";
-const OUTPUT_DIR: &str = "../docs";
-
-fn main() {
-    let upsert_only = is_upsert_only();
-    run(upsert_only);
-}
-
-fn is_upsert_only() -> bool {
-    let args: Vec<String> = std::env::args().collect();
-
-    for arg in &args[1..] {
-        // skip program name at args[0]
-        match arg.as_str() {
-            "--upsert-only" | "-u" => {
-                println!("read --upsert-only flag");
-                return true;
-            }
-            _ => {}
+use blag_compiler::cli::{Cli, Command, Parsed};
+use blag_compiler::commands::Commands;
+use blag_compiler::config::Config;
+use blag_compiler::serve;
+use std::process::ExitCode;
+
+// exit code for bad command line usage, separate from runtime failures (1)
+const USAGE_EXIT_CODE: u8 = 2;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect(); // skip program name
+
+    let command = match Cli::parse(&args) {
+        Ok(Parsed::Run(command)) => command,
+        Ok(Parsed::Help(text)) => {
+            print!("{}", text);
+            return ExitCode::SUCCESS;
         }
-    }
-
-    false
-}
-
-fn run(upsert_only: bool) {
-    println!("generating static site...");
-
-    let mut info_wrangler = info::InfoWrangler::init();
-    let article_paths = articles::Articles::must_get_article_paths();
-
-    for path in &article_paths {
-        info_wrangler.upsert(path);
-    }
-
-    // TODO: clean the info file of articles that no longer exist?
-
-    if upsert_only {
-        info_wrangler.save();
-        println!("done!");
-        return;
-    }
+        Err(message) => {
+            eprintln!("error: {}\n", message);
+            eprint!("{}", Cli::main_help());
+            return ExitCode::from(USAGE_EXIT_CODE);
+        }
+    };
 
-    for path in &article_paths {
-        articles::Articles::process(path, &mut info_wrangler);
+    let config = match Config::load(&args) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match command {
+        Command::Build {
+            upsert_only,
+            prune,
+            dry_run,
+            force,
+        } => Commands::build(&config, upsert_only, prune, dry_run, force),
+        Command::New { title } => Commands::new_article(&config, &title),
+        Command::Check => Commands::check(&config),
+        Command::Clean => Commands::clean(&config),
+        Command::Watch => Commands::watch(&config),
+        Command::SuggestTags { count, write } => Commands::suggest_tags(&config, count, write),
+        Command::Serve { port } => serve::Server::run(&config, port),
     }
-
-    info_wrangler.save();
-    html::IndexHtml::save(&mut info_wrangler);
-
-    println!("done!");
 }
@@ -0,0 +1,79 @@
+use pulldown_cmark::Options;
+use serde::Deserialize;
+
+// which CommonMark extensions articles are rendered with
+// set for the whole site in the [markdown] table of the config file,
+// and per article under "markdown" in its front matter
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarkdownOptions {
+    pub tables: bool,             // GitHub-style | a | b | tables
+    pub footnotes: bool,          // [^1] references and [^1]: definitions
+    pub strikethrough: bool,      // ~~struck~~
+    pub tasklists: bool,          // - [x] done
+    pub heading_attributes: bool, // # Heading {#id .class}
+    pub smart_punctuation: bool,  // curly quotes, en and em dashes, ellipses
+    pub math: bool,               // $inline$ and $$display$$ LaTeX, rendered to MathML
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        MarkdownOptions {
+            tables: true,
+            footnotes: true,
+            strikethrough: true,
+            tasklists: true,
+            heading_attributes: true,
+            smart_punctuation: true,
+            math: true,
+        }
+    }
+}
+
+// the front matter version, anything left out falls back to the site's setting
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarkdownOverrides {
+    pub tables: Option<bool>,
+    pub footnotes: Option<bool>,
+    pub strikethrough: Option<bool>,
+    pub tasklists: Option<bool>,
+    pub heading_attributes: Option<bool>,
+    pub smart_punctuation: Option<bool>,
+    pub math: Option<bool>,
+}
+
+impl MarkdownOptions {
+    pub fn with_overrides(&self, overrides: &MarkdownOverrides) -> Self {
+        MarkdownOptions {
+            tables: overrides.tables.unwrap_or(self.tables),
+            footnotes: overrides.footnotes.unwrap_or(self.footnotes),
+            strikethrough: overrides.strikethrough.unwrap_or(self.strikethrough),
+            tasklists: overrides.tasklists.unwrap_or(self.tasklists),
+            heading_attributes: overrides
+                .heading_attributes
+                .unwrap_or(self.heading_attributes),
+            smart_punctuation: overrides
+                .smart_punctuation
+                .unwrap_or(self.smart_punctuation),
+            math: overrides.math.unwrap_or(self.math),
+        }
+    }
+
+    pub fn parser_options(&self) -> Options {
+        let mut options = Options::empty();
+        options.set(Options::ENABLE_TABLES, self.tables);
+        options.set(Options::ENABLE_FOOTNOTES, self.footnotes);
+        options.set(Options::ENABLE_STRIKETHROUGH, self.strikethrough);
+        options.set(Options::ENABLE_TASKLISTS, self.tasklists);
+        options.set(Options::ENABLE_HEADING_ATTRIBUTES, self.heading_attributes);
+        options.set(Options::ENABLE_SMART_PUNCTUATION, self.smart_punctuation);
+        options.set(Options::ENABLE_MATH, self.math);
+        options
+    }
+
+    // for the config's render fingerprint
+    pub fn fingerprint(&self) -> String {
+        self.parser_options().bits().to_string()
+    }
+}
@@ -0,0 +1,840 @@
+use crate::utils::Utils;
+
+// turns the LaTeX between $...$ and $$...$$ into MathML, so browsers draw it without any javascript
+// only a subset of LaTeX is understood, the kind of thing that shows up in a blog post:
+// - letters, numbers and operators, ^ and _ scripts, {} groups
+// - \frac, \sqrt, \binom, \left \right, accents like \hat, fonts like \mathbf, \text
+// - greek letters, common symbols and relations, big operators like \sum, functions like \log
+// - matrix, pmatrix, bmatrix, vmatrix, cases and aligned environments
+// anything else is shown in red as an error, instead of failing the build
+pub struct Math {}
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Command(String),
+    Char(char),
+    Open,
+    Close,
+    Sup,
+    Sub,
+    Align,
+    NewRow,
+}
+
+struct Node {
+    html: String,
+    limits: bool, // scripts go above and below (like \sum) instead of to the side
+}
+
+impl Node {
+    fn new(html: String) -> Self {
+        Node {
+            html,
+            limits: false,
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    display: bool,
+}
+
+impl Math {
+    pub fn to_mathml(latex: &str, display: bool) -> String {
+        let mut parser = Parser {
+            tokens: Self::tokenize(latex),
+            pos: 0,
+            display,
+        };
+
+        let mut nodes = Vec::new();
+        loop {
+            nodes.extend(parser.parse_row());
+            // a stray }, &, \\, \right or \end at the top level, skip it and keep going
+            match parser.next() {
+                None => break,
+                // with its {name}, so that doesn't show up as letters
+                Some(Token::Command(name)) if name == "end" => {
+                    parser.raw_group();
+                }
+                Some(_) => {}
+            }
+        }
+
+        format!(
+            r#"<math xmlns="http://www.w3.org/1998/Math/MathML"{}><semantics><mrow>{}</mrow><annotation encoding="application/x-tex">{}</annotation></semantics></math>"#,
+            if display { r#" display="block""# } else { "" },
+            nodes.concat(),
+            Utils::escape_html(latex.trim())
+        )
+    }
+
+    fn tokenize(latex: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut chars = latex.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let token = match c {
+                '{' => Token::Open,
+                '}' => Token::Close,
+                '^' => Token::Sup,
+                '_' => Token::Sub,
+                '&' => Token::Align,
+                '\\' => match chars.peek() {
+                    Some('\\') => {
+                        chars.next();
+                        Token::NewRow
+                    }
+                    Some(c) if c.is_ascii_alphabetic() => {
+                        let mut name = String::new();
+                        while let Some(c) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                            name.push(*c);
+                            chars.next();
+                        }
+                        Token::Command(name)
+                    }
+                    Some(_) => Token::Command(chars.next().unwrap_or(' ').to_string()),
+                    None => Token::Char('\\'),
+                },
+                c if c.is_whitespace() => Token::Char(' '),
+                c => Token::Char(c),
+            };
+            tokens.push(token);
+        }
+
+        tokens
+    }
+}
+
+impl Parser {
+    fn peek(&mut self) -> Option<&Token> {
+        // spaces only matter inside \text, which reads its argument with raw_group instead
+        while self.tokens.get(self.pos) == Some(&Token::Char(' ')) {
+            self.pos += 1;
+        }
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    // nodes until the end of the current group, cell or row
+    fn parse_row(&mut self) -> Vec<String> {
+        let mut nodes = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(Token::Close) | Some(Token::Align) | Some(Token::NewRow) => break,
+                Some(Token::Command(name)) if name == "right" || name == "end" => break,
+                _ => {}
+            }
+            let base = self.parse_atom();
+            nodes.push(self.parse_scripts(base));
+        }
+        nodes
+    }
+
+    fn parse_scripts(&mut self, base: Node) -> String {
+        let mut sub = None;
+        let mut sup = None;
+        loop {
+            match self.peek() {
+                Some(Token::Sub) if sub.is_none() => {
+                    self.next();
+                    sub = Some(self.parse_argument());
+                }
+                Some(Token::Sup) if sup.is_none() => {
+                    self.next();
+                    sup = Some(self.parse_argument());
+                }
+                Some(Token::Char('\'')) if sup.is_none() => {
+                    self.next();
+                    sup = Some("<mo>′</mo>".to_string());
+                }
+                _ => break,
+            }
+        }
+
+        let limits = base.limits && self.display;
+        match (sub, sup) {
+            (None, None) => base.html,
+            (Some(sub), None) if limits => format!("<munder>{}{}</munder>", base.html, sub),
+            (Some(sub), None) => format!("<msub>{}{}</msub>", base.html, sub),
+            (None, Some(sup)) if limits => format!("<mover>{}{}</mover>", base.html, sup),
+            (None, Some(sup)) => format!("<msup>{}{}</msup>", base.html, sup),
+            (Some(sub), Some(sup)) if limits => {
+                format!("<munderover>{}{}{}</munderover>", base.html, sub, sup)
+            }
+            (Some(sub), Some(sup)) => {
+                format!("<msubsup>{}{}{}</msubsup>", base.html, sub, sup)
+            }
+        }
+    }
+
+    // a {group}, or else a single token, like the 2 in x^2
+    fn parse_argument(&mut self) -> String {
+        match self.peek() {
+            Some(Token::Open) => self.parse_group(),
+            Some(Token::Char(c)) if c.is_ascii_digit() => {
+                let c = *c;
+                self.next();
+                format!("<mn>{}</mn>", c)
+            }
+            None | Some(Token::Close) => "<mrow></mrow>".to_string(),
+            _ => self.parse_atom().html,
+        }
+    }
+
+    fn parse_group(&mut self) -> String {
+        self.next(); // {
+        let nodes = self.parse_row();
+        if self.peek() == Some(&Token::Close) {
+            self.next();
+        }
+        format!("<mrow>{}</mrow>", nodes.concat())
+    }
+
+    // the text of a {group} as written, for \text and environment names
+    fn raw_group(&mut self) -> String {
+        if self.peek() != Some(&Token::Open) {
+            return String::new();
+        }
+        self.next();
+
+        let mut text = String::new();
+        let mut depth = 0;
+        while let Some(token) = self.tokens.get(self.pos).cloned() {
+            self.pos += 1;
+            match token {
+                Token::Open => depth += 1,
+                Token::Close if depth == 0 => break,
+                Token::Close => depth -= 1,
+                Token::Char(c) => text.push(c),
+                Token::Command(name) if name.len() == 1 => text.push_str(&name),
+                Token::Command(name) => text.push_str(&format!("\\{}", name)),
+                Token::Sup => text.push('^'),
+                Token::Sub => text.push('_'),
+                Token::Align => text.push('&'),
+                Token::NewRow => text.push_str("\\\\"),
+            }
+        }
+        text
+    }
+
+    fn parse_atom(&mut self) -> Node {
+        let Some(token) = self.peek().cloned() else {
+            return Node::new("<mrow></mrow>".to_string());
+        };
+
+        match token {
+            Token::Open => Node::new(self.parse_group()),
+            // a script with nothing before it
+            Token::Sub | Token::Sup => Node::new("<mrow></mrow>".to_string()),
+            Token::Char(c) if c.is_ascii_digit() || c == '.' => {
+                let mut number = String::new();
+                while let Some(Token::Char(c)) = self.tokens.get(self.pos)
+                    && (c.is_ascii_digit() || *c == '.')
+                {
+                    number.push(*c);
+                    self.pos += 1;
+                }
+                Node::new(format!("<mn>{}</mn>", number))
+            }
+            Token::Char(c) if c.is_alphabetic() => {
+                self.next();
+                Node::new(format!("<mi>{}</mi>", c))
+            }
+            Token::Char(c) => {
+                self.next();
+                Node::new(Self::operator(&c.to_string()))
+            }
+            Token::Command(name) => {
+                self.next();
+                self.parse_command(&name)
+            }
+            // parse_row never gets here with these
+            Token::Close | Token::Align | Token::NewRow => {
+                self.next();
+                Node::new("<mrow></mrow>".to_string())
+            }
+        }
+    }
+
+    fn parse_command(&mut self, name: &str) -> Node {
+        if let Some(symbol) = Self::identifier_symbol(name) {
+            let variant = if symbol.chars().next().is_some_and(|c| c.is_uppercase()) {
+                r#" mathvariant="normal""#
+            } else {
+                ""
+            };
+            return Node::new(format!(
+                "<mi{}>{}</mi>",
+                variant,
+                Utils::escape_html(symbol)
+            ));
+        }
+        if let Some(symbol) = Self::operator_symbol(name) {
+            return Node::new(Self::operator(symbol));
+        }
+        if let Some(symbol) = Self::big_operator_symbol(name) {
+            // integrals keep their bounds to the side, even in display math
+            return Node {
+                html: Self::operator(symbol),
+                limits: !name.ends_with("int"),
+            };
+        }
+        if let Some(width) = Self::space_width(name) {
+            return Node::new(format!(r#"<mspace width="{}"/>"#, width));
+        }
+        if FUNCTIONS.contains(&name) {
+            return Node {
+                html: format!("<mi>{}</mi>", name),
+                limits: LIMIT_FUNCTIONS.contains(&name),
+            };
+        }
+
+        let html = match name {
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.parse_argument();
+                let denominator = self.parse_argument();
+                format!("<mfrac>{}{}</mfrac>", numerator, denominator)
+            }
+            "binom" => {
+                let n = self.parse_argument();
+                let k = self.parse_argument();
+                format!(
+                    r#"<mrow><mo>(</mo><mfrac linethickness="0">{}{}</mfrac><mo>)</mo></mrow>"#,
+                    n, k
+                )
+            }
+            "sqrt" => {
+                let index = self.optional_argument();
+                let radicand = self.parse_argument();
+                match index {
+                    Some(index) => format!("<mroot>{}{}</mroot>", radicand, index),
+                    None => format!("<msqrt>{}</msqrt>", radicand),
+                }
+            }
+            "text" | "textrm" | "mbox" => {
+                format!("<mtext>{}</mtext>", Utils::escape_html(&self.raw_group()))
+            }
+            "operatorname" => {
+                let text = self.raw_group();
+                return Node {
+                    html: format!("<mi>{}</mi>", Utils::escape_html(&text)),
+                    limits: false,
+                };
+            }
+            "mathrm" | "mathbf" | "mathit" | "mathbb" | "mathcal" | "mathsf" | "mathtt" => {
+                let variant = match name {
+                    "mathrm" => "normal",
+                    "mathbf" => "bold",
+                    "mathit" => "italic",
+                    "mathbb" => "double-struck",
+                    "mathcal" => "script",
+                    "mathsf" => "sans-serif",
+                    _ => "monospace",
+                };
+                let text = self.raw_group();
+                format!(
+                    r#"<mi mathvariant="{}">{}</mi>"#,
+                    variant,
+                    Utils::escape_html(&text)
+                )
+            }
+            "hat" | "widehat" | "bar" | "overline" | "vec" | "tilde" | "widetilde" | "dot"
+            | "ddot" => {
+                let accent = match name {
+                    "hat" | "widehat" => "^",
+                    "bar" | "overline" => "¯",
+                    "vec" => "→",
+                    "tilde" | "widetilde" => "~",
+                    "dot" => "˙",
+                    _ => "¨",
+                };
+                let base = self.parse_argument();
+                format!(
+                    r#"<mover accent="true">{}<mo>{}</mo></mover>"#,
+                    base, accent
+                )
+            }
+            "underline" => {
+                let base = self.parse_argument();
+                format!(r#"<munder accentunder="true">{}<mo>_</mo></munder>"#, base)
+            }
+            "left" => self.parse_fenced(),
+            "begin" => self.parse_environment(),
+            // stray \right or \end, parse_row stops before these
+            "right" => String::new(),
+            "end" => {
+                self.raw_group();
+                String::new()
+            }
+            _ => Self::error(&format!("\\{}", name)),
+        };
+        Node::new(html)
+    }
+
+    // the [n] of \sqrt[n]{x}
+    fn optional_argument(&mut self) -> Option<String> {
+        if self.peek() != Some(&Token::Char('[')) {
+            return None;
+        }
+        self.next();
+
+        let mut nodes = Vec::new();
+        while !matches!(self.peek(), None | Some(Token::Char(']'))) {
+            let base = self.parse_atom();
+            nodes.push(self.parse_scripts(base));
+        }
+        self.next();
+        Some(format!("<mrow>{}</mrow>", nodes.concat()))
+    }
+
+    // \left( ... \right), the delimiters stretch to fit
+    fn parse_fenced(&mut self) -> String {
+        let open = self.delimiter();
+        let inner = self.parse_row().concat();
+        let close = match self.peek() {
+            Some(Token::Command(name)) if name == "right" => {
+                self.next();
+                self.delimiter()
+            }
+            _ => String::new(),
+        };
+        format!("<mrow>{}{}{}</mrow>", open, inner, close)
+    }
+
+    fn delimiter(&mut self) -> String {
+        let symbol = match self.next() {
+            Some(Token::Char('.')) | None => return String::new(),
+            Some(Token::Char(c)) => c.to_string(),
+            Some(Token::Command(name)) => match name.as_str() {
+                "{" | "lbrace" => "{".to_string(),
+                "}" | "rbrace" => "}".to_string(),
+                "langle" => "⟨".to_string(),
+                "rangle" => "⟩".to_string(),
+                "|" | "Vert" => "‖".to_string(),
+                "lfloor" => "⌊".to_string(),
+                "rfloor" => "⌋".to_string(),
+                "lceil" => "⌈".to_string(),
+                "rceil" => "⌉".to_string(),
+                _ => return Self::error(&format!("\\{}", name)),
+            },
+            Some(_) => return String::new(),
+        };
+        format!(
+            r#"<mo stretchy="true">{}</mo>"#,
+            Utils::escape_html(&symbol)
+        )
+    }
+
+    // \begin{name} cells & separated, rows \\ separated \end{name}
+    fn parse_environment(&mut self) -> String {
+        let name = self.raw_group();
+        let (open, close, column_align) = match name.as_str() {
+            "matrix" => ("", "", None),
+            "pmatrix" => ("(", ")", None),
+            "bmatrix" => ("[", "]", None),
+            "vmatrix" => ("|", "|", None),
+            "cases" => ("{", "", Some("left left")),
+            "aligned" | "align" | "align*" | "split" => ("", "", Some("right left")),
+            _ => return Self::error(&format!("\\begin{{{}}}", name)),
+        };
+
+        let mut rows = Vec::new();
+        loop {
+            let mut cells = Vec::new();
+            loop {
+                let cell = self.parse_row().concat();
+                cells.push(format!("<mtd>{}</mtd>", cell));
+                if self.peek() != Some(&Token::Align) {
+                    break;
+                }
+                self.next();
+            }
+            rows.push(format!("<mtr>{}</mtr>", cells.concat()));
+            match self.peek() {
+                Some(Token::NewRow) => {
+                    self.next();
+                }
+                // a stray } inside the environment, skip it
+                Some(Token::Close) => {
+                    self.next();
+                }
+                _ => break,
+            }
+        }
+        if let Some(Token::Command(end)) = self.peek()
+            && end == "end"
+        {
+            self.next();
+            self.raw_group();
+        }
+
+        let column_align = column_align
+            .map(|align| format!(r#" columnalign="{}""#, align))
+            .unwrap_or_default();
+        let fence = |symbol: &str| {
+            if symbol.is_empty() {
+                String::new()
+            } else {
+                Self::operator(symbol)
+            }
+        };
+        format!(
+            "<mrow>{}<mtable{}>{}</mtable>{}</mrow>",
+            fence(open),
+            column_align,
+            rows.concat(),
+            fence(close)
+        )
+    }
+
+    fn operator(symbol: &str) -> String {
+        format!("<mo>{}</mo>", Utils::escape_html(symbol))
+    }
+
+    fn error(text: &str) -> String {
+        format!(
+            r#"<merror><mtext>{}</mtext></merror>"#,
+            Utils::escape_html(text)
+        )
+    }
+
+    fn identifier_symbol(name: &str) -> Option<&'static str> {
+        let symbol = match name {
+            "alpha" => "α",
+            "beta" => "β",
+            "gamma" => "γ",
+            "delta" => "δ",
+            "epsilon" => "ϵ",
+            "varepsilon" => "ε",
+            "zeta" => "ζ",
+            "eta" => "η",
+            "theta" => "θ",
+            "vartheta" => "ϑ",
+            "iota" => "ι",
+            "kappa" => "κ",
+            "lambda" => "λ",
+            "mu" => "μ",
+            "nu" => "ν",
+            "xi" => "ξ",
+            "pi" => "π",
+            "rho" => "ρ",
+            "sigma" => "σ",
+            "tau" => "τ",
+            "upsilon" => "υ",
+            "phi" => "ϕ",
+            "varphi" => "φ",
+            "chi" => "χ",
+            "psi" => "ψ",
+            "omega" => "ω",
+            "Gamma" => "Γ",
+            "Delta" => "Δ",
+            "Theta" => "Θ",
+            "Lambda" => "Λ",
+            "Xi" => "Ξ",
+            "Pi" => "Π",
+            "Sigma" => "Σ",
+            "Upsilon" => "Υ",
+            "Phi" => "Φ",
+            "Psi" => "Ψ",
+            "Omega" => "Ω",
+            "infty" => "∞",
+            "ell" => "ℓ",
+            "emptyset" | "varnothing" => "∅",
+            "partial" => "∂",
+            "nabla" => "∇",
+            "_" => "_",
+            "#" => "#",
+            "$" => "$",
+            "%" => "%",
+            "&" => "&",
+            _ => return None,
+        };
+        Some(symbol)
+    }
+
+    fn operator_symbol(name: &str) -> Option<&'static str> {
+        let symbol = match name {
+            "cdot" => "⋅",
+            "times" => "×",
+            "div" => "÷",
+            "pm" => "±",
+            "mp" => "∓",
+            "ast" => "∗",
+            "circ" => "∘",
+            "le" | "leq" => "≤",
+            "ge" | "geq" => "≥",
+            "ne" | "neq" => "≠",
+            "approx" => "≈",
+            "sim" => "∼",
+            "simeq" => "≃",
+            "equiv" => "≡",
+            "propto" => "∝",
+            "ll" => "≪",
+            "gg" => "≫",
+            "in" => "∈",
+            "notin" => "∉",
+            "subset" => "⊂",
+            "subseteq" => "⊆",
+            "supset" => "⊃",
+            "supseteq" => "⊇",
+            "cup" => "∪",
+            "cap" => "∩",
+            "setminus" => "∖",
+            "land" | "wedge" => "∧",
+            "lor" | "vee" => "∨",
+            "neg" | "lnot" => "¬",
+            "forall" => "∀",
+            "exists" => "∃",
+            "to" | "rightarrow" => "→",
+            "leftarrow" | "gets" => "←",
+            "Rightarrow" | "implies" => "⇒",
+            "Leftarrow" => "⇐",
+            "leftrightarrow" => "↔",
+            "Leftrightarrow" | "iff" => "⇔",
+            "mapsto" => "↦",
+            "ldots" | "dots" => "…",
+            "cdots" => "⋯",
+            "vdots" => "⋮",
+            "ddots" => "⋱",
+            "mid" => "∣",
+            "|" => "‖",
+            "{" | "lbrace" => "{",
+            "}" | "rbrace" => "}",
+            "langle" => "⟨",
+            "rangle" => "⟩",
+            "lfloor" => "⌊",
+            "rfloor" => "⌋",
+            "lceil" => "⌈",
+            "rceil" => "⌉",
+            "oplus" => "⊕",
+            "otimes" => "⊗",
+            _ => return None,
+        };
+        Some(symbol)
+    }
+
+    fn big_operator_symbol(name: &str) -> Option<&'static str> {
+        let symbol = match name {
+            "sum" => "∑",
+            "prod" => "∏",
+            "coprod" => "∐",
+            "int" => "∫",
+            "iint" => "∬",
+            "oint" => "∮",
+            "bigcup" => "⋃",
+            "bigcap" => "⋂",
+            "bigoplus" => "⨁",
+            _ => return None,
+        };
+        Some(symbol)
+    }
+
+    fn space_width(name: &str) -> Option<&'static str> {
+        let width = match name {
+            "," | "thinspace" => "0.1667em",
+            ":" | ">" | "medspace" => "0.2222em",
+            ";" | "thickspace" => "0.2778em",
+            " " => "0.25em",
+            "quad" => "1em",
+            "qquad" => "2em",
+            "!" => "0em",
+            _ => return None,
+        };
+        Some(width)
+    }
+}
+
+// written upright, like \log
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "log", "ln", "lg", "exp", "det", "dim", "deg", "gcd", "hom", "ker", "arg", "Pr", "lim",
+    "liminf", "limsup", "min", "max", "inf", "sup", "argmin", "argmax",
+];
+
+// functions whose scripts go underneath in display math, like \lim_{x \to 0}
+const LIMIT_FUNCTIONS: &[&str] = &[
+    "lim", "liminf", "limsup", "min", "max", "inf", "sup", "argmin", "argmax", "Pr", "det", "gcd",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // just the MathML inside the outer <mrow>, without the annotation
+    fn inner(latex: &str, display: bool) -> String {
+        let html = Math::to_mathml(latex, display);
+        let start = html.find("<semantics><mrow>").unwrap() + "<semantics><mrow>".len();
+        let end = html.rfind("</mrow><annotation").unwrap();
+        html[start..end].to_string()
+    }
+
+    // every tag that's opened is closed again, in the right order
+    fn assert_balanced(html: &str) {
+        let tag = regex::Regex::new(r"<(/?)([a-z]+)[^>]*?(/?)>").unwrap();
+        let mut open = Vec::new();
+        for caps in tag.captures_iter(html) {
+            let name = &caps[2];
+            if &caps[3] == "/" {
+                continue;
+            }
+            if &caps[1] == "/" {
+                assert_eq!(open.pop(), Some(name.to_string()), "{}", html);
+            } else {
+                open.push(name.to_string());
+            }
+        }
+        assert!(open.is_empty(), "{}", html);
+    }
+
+    #[test]
+    fn frac() {
+        assert_eq!(
+            inner(r"\frac{a}{b}", false),
+            "<mfrac><mrow><mi>a</mi></mrow><mrow><mi>b</mi></mrow></mfrac>"
+        );
+        assert_eq!(
+            inner(r"\frac12", false),
+            "<mfrac><mn>1</mn><mn>2</mn></mfrac>"
+        );
+    }
+
+    #[test]
+    fn scripts() {
+        assert_eq!(inner("x^2", false), "<msup><mi>x</mi><mn>2</mn></msup>");
+        assert_eq!(inner("x_i", false), "<msub><mi>x</mi><mi>i</mi></msub>");
+        assert_eq!(
+            inner("x_i^{10}", false),
+            "<msubsup><mi>x</mi><mi>i</mi><mrow><mn>10</mn></mrow></msubsup>"
+        );
+        assert_eq!(inner("f'", false), "<msup><mi>f</mi><mo>′</mo></msup>");
+    }
+
+    #[test]
+    fn limits_only_in_display_math() {
+        assert_eq!(
+            inner(r"\sum_{i}^n", true),
+            "<munderover><mo>∑</mo><mrow><mi>i</mi></mrow><mi>n</mi></munderover>"
+        );
+        assert_eq!(
+            inner(r"\sum_{i}^n", false),
+            "<msubsup><mo>∑</mo><mrow><mi>i</mi></mrow><mi>n</mi></msubsup>"
+        );
+        // integrals keep their bounds to the side
+        assert_eq!(
+            inner(r"\int_0^1", true),
+            "<msubsup><mo>∫</mo><mn>0</mn><mn>1</mn></msubsup>"
+        );
+    }
+
+    #[test]
+    fn left_right() {
+        assert_eq!(
+            inner(r"\left( x \right)", false),
+            r#"<mrow><mo stretchy="true">(</mo><mi>x</mi><mo stretchy="true">)</mo></mrow>"#
+        );
+        assert_eq!(
+            inner(r"\left\{ x \right.", false),
+            r#"<mrow><mo stretchy="true">{</mo><mi>x</mi></mrow>"#
+        );
+    }
+
+    #[test]
+    fn environments() {
+        assert_eq!(
+            inner(r"\begin{pmatrix} a & b \\ c & d \end{pmatrix}", false),
+            "<mrow><mo>(</mo><mtable>\
+             <mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr>\
+             <mtr><mtd><mi>c</mi></mtd><mtd><mi>d</mi></mtd></mtr>\
+             </mtable><mo>)</mo></mrow>"
+        );
+        assert_eq!(
+            inner(r"\begin{cases} 1 & x > 0 \end{cases}", false),
+            r#"<mrow><mo>{</mo><mtable columnalign="left left"><mtr><mtd><mn>1</mn></mtd><mtd><mi>x</mi><mo>&gt;</mo><mn>0</mn></mtd></mtr></mtable></mrow>"#
+        );
+    }
+
+    #[test]
+    fn unknown_commands_are_errors() {
+        assert_eq!(
+            inner(r"\nope", false),
+            "<merror><mtext>\\nope</mtext></merror>"
+        );
+        assert_eq!(
+            inner(r"\begin{nope} x \end{nope}", false),
+            "<merror><mtext>\\begin{nope}</mtext></merror><mi>x</mi>"
+        );
+    }
+
+    #[test]
+    fn escapes_html() {
+        assert_eq!(inner("a < b", false), "<mi>a</mi><mo>&lt;</mo><mi>b</mi>");
+        assert_eq!(inner(r"\&", false), "<mi>&amp;</mi>");
+        assert_eq!(
+            inner(r"\text{<b>'s}", false),
+            "<mtext>&lt;b&gt;&#39;s</mtext>"
+        );
+        assert_eq!(
+            inner(r"\mathbf{<}", false),
+            r#"<mi mathvariant="bold">&lt;</mi>"#
+        );
+        assert!(
+            Math::to_mathml("a<b", false)
+                .contains(r#"encoding="application/x-tex">a&lt;b</annotation>"#)
+        );
+    }
+
+    #[test]
+    fn bad_input_still_renders() {
+        let inputs = [
+            "",
+            "}",
+            "{",
+            "{{{",
+            "}}}",
+            "\\",
+            "\\\\",
+            "^",
+            "_^_'",
+            "x^^2",
+            "&&",
+            r"\left",
+            r"\left(",
+            r"\right)",
+            r"\left( \right",
+            r"\end{matrix}",
+            r"\begin",
+            r"\begin{",
+            r"\begin{matrix}",
+            r"\begin{matrix} a & } b \\",
+            r"\left( \begin{cases} a & b",
+            r"\sqrt[",
+            r"\sqrt[}]",
+            r"\sqrt[3",
+            r"\frac",
+            r"\frac{",
+            r"\text{",
+            r"\operatorname",
+            r"x^{y_{z",
+            r"\hat",
+            r"\",
+        ];
+        for latex in inputs {
+            for display in [false, true] {
+                let html = Math::to_mathml(latex, display);
+                assert!(html.starts_with("<math"), "{}", html);
+                assert!(html.ends_with("</math>"), "{}", html);
+                assert_balanced(&html);
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// the index page's fuzzy search, the build half of it
+// each article gets bloom filters of the trigrams in its title, its tags and its body,
+// the page checks which of the search term's trigrams are in each and adds up the weighted counts
+// everything here has to give exactly the same bits as the javascript in html.rs
+pub struct Search {}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchOptions {
+    // how much a matching trigram counts for, depending on where it matched
+    pub title_weight: u32,
+    pub tag_weight: u32,
+    pub body_weight: u32, // 0 turns body search off
+    // every filter is sized (and given as many hashes as it needs) to get close to this
+    pub false_positive_rate: f64,
+    pub max_hashes: u32,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            title_weight: 3,
+            tag_weight: 2,
+            body_weight: 1,
+            false_positive_rate: 0.05,
+            max_hashes: 3,
+        }
+    }
+}
+
+// a bloom filter, with what the page needs to look things up in it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Bloom {
+    pub trigrams: usize, // how many went in, so it can be rebuilt if the options change
+    pub bits: u64,       // m, always a whole number of bytes
+    pub hashes: u32,     // k
+    pub filter: String,  // base64 encoded
+}
+
+// even an empty title gets a filter the page can read
+const MIN_BITS: u64 = 64;
+// positions come from the top 20 bits of the hash, so bigger filters wouldn't fill up
+const MAX_BITS: u64 = 1 << 20;
+
+impl Search {
+    pub fn validate(options: &SearchOptions) -> Result<(), String> {
+        if !(options.false_positive_rate > 0.0 && options.false_positive_rate < 1.0) {
+            return Err(format!(
+                "search false_positive_rate must be between 0 and 1, not {}",
+                options.false_positive_rate
+            ));
+        }
+        if options.max_hashes < 1 {
+            return Err("search max_hashes must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn title_bloom(options: &SearchOptions, title: &str) -> Bloom {
+        Self::bloom(options, &Self::build_trigrams(&Self::squash_text(title)))
+    }
+
+    // every tag goes in the same filter, trigrams don't span tags
+    pub fn tag_bloom(options: &SearchOptions, tags: &[String]) -> Bloom {
+        let mut trigrams = BTreeSet::new();
+        for tag in tags {
+            trigrams.extend(Self::build_trigrams(&Self::squash_text(tag)));
+        }
+        Self::bloom(options, &trigrams)
+    }
+
+    // from the body's plain text
+    pub fn body_bloom(options: &SearchOptions, text: &str) -> Bloom {
+        Self::bloom(options, &Self::build_trigrams(&Self::squash_text(text)))
+    }
+
+    // whether the filter was built with what the options ask for now
+    pub fn is_current(options: &SearchOptions, bloom: &Bloom) -> bool {
+        Self::size(options, bloom.trigrams) == (bloom.bits, bloom.hashes)
+    }
+
+    fn bloom(options: &SearchOptions, trigrams: &BTreeSet<String>) -> Bloom {
+        let (bits, hashes) = Self::size(options, trigrams.len());
+        let filter = Self::blossom(trigrams, bits, hashes);
+        Bloom {
+            trigrams: trigrams.len(),
+            bits,
+            hashes,
+            filter: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, filter),
+        }
+    }
+
+    // the textbook sizes for n elements and a false positive rate p:
+    // m = -n ln(p) / ln(2)^2 bits, and k = (m / n) ln(2) hashes
+    // fewer hashes than that (max_hashes) are made up for with more bits
+    pub fn size(options: &SearchOptions, trigrams: usize) -> (u64, u32) {
+        let n = trigrams.max(1) as f64;
+        let p = options.false_positive_rate;
+        let ln2 = std::f64::consts::LN_2;
+
+        let optimal_bits = -n * p.ln() / (ln2 * ln2);
+        let hashes = ((optimal_bits / n) * ln2)
+            .round()
+            .clamp(1.0, options.max_hashes as f64);
+        // solve p = (1 - e^(-kn/m))^k for m, with k fixed
+        let bits = -hashes * n / (1.0 - p.powf(1.0 / hashes)).ln();
+
+        let bits = (bits.ceil() as u64).div_ceil(8) * 8;
+        (bits.clamp(MIN_BITS, MAX_BITS), hashes as u32)
+    }
+
+    // squashText: lowercase, then keep only a-z, 0-9 and #
+    pub fn squash_text(text: &str) -> String {
+        text.to_lowercase()
+            .chars()
+            .filter(|c| matches!(c, 'a'..='z' | '0'..='9' | '#'))
+            .collect()
+    }
+
+    // buildTrigrams: every 3 character chunk, squashed text is all ascii so bytes are characters
+    pub fn build_trigrams(text: &str) -> BTreeSet<String> {
+        text.as_bytes()
+            .windows(3)
+            .filter_map(|window| std::str::from_utf8(window).ok())
+            .map(|trigram| trigram.to_string())
+            .collect()
+    }
+
+    // djb2tri: javascript does this in 32 bit integers, so wrap the same way
+    // Math.abs at the end means the result fits in 0..=2^31
+    pub fn djb2tri(trigram: &str) -> u64 {
+        let bytes = trigram.as_bytes();
+        let mut h: i32 = 5381;
+        for i in 0..33 {
+            let c = bytes.get(i % 3).copied().unwrap_or(0) as i32;
+            h = (h.wrapping_shl(5)).wrapping_add(h) ^ c;
+        }
+        Self::js_abs(h)
+    }
+
+    // Math.abs of a 32 bit integer, -2^31 comes out as 2^31 (not wrapped back round like i32::abs)
+    fn js_abs(h: i32) -> u64 {
+        (h as i64).unsigned_abs()
+    }
+
+    // bitPositions: k positions from the one hash, stepping by its top bits (double hashing)
+    // plain division instead of javascript's >>, which would go through a signed 32 bit integer
+    pub fn bit_positions(hash: u64, bits: u64, hashes: u32) -> impl Iterator<Item = u64> {
+        let step = hash / 2048 + 1;
+        (0..hashes as u64).map(move |i| (hash + i * step) % bits)
+    }
+
+    pub fn blossom(trigrams: &BTreeSet<String>, bits: u64, hashes: u32) -> Vec<u8> {
+        let mut result = vec![0u8; (bits / 8) as usize];
+        for trigram in trigrams {
+            for bit_pos in Self::bit_positions(Self::djb2tri(trigram), bits, hashes) {
+                result[(bit_pos / 8) as usize] |= 1 << (bit_pos % 8);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the expected values all come from running the javascript in html.rs
+
+    fn encoded_blossom(text: &str, bits: u64, hashes: u32) -> String {
+        let trigrams = Search::build_trigrams(&Search::squash_text(text));
+        base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            Search::blossom(&trigrams, bits, hashes),
+        )
+    }
+
+    #[test]
+    fn squash_text() {
+        assert_eq!(
+            Search::squash_text("Hello, World! #This\nis a test."),
+            "helloworld#thisisatest"
+        );
+        assert_eq!(
+            Search::squash_text("C# vs F#, 100% 2024-01-01"),
+            "c#vsf#10020240101"
+        );
+        // accents go, the kelvin sign lowercases to k, fullwidth letters stay fullwidth and go
+        assert_eq!(
+            Search::squash_text("Café Ünïcode: İstanbul & ＫＥＬＶＩＮ (\u{212a}) straße"),
+            "cafncodeistanbulkstrae"
+        );
+    }
+
+    #[test]
+    fn djb2tri() {
+        for (trigram, hash) in [
+            ("hel", 1223377660),
+            ("abc", 1666894213),
+            ("zzz", 456590881),
+            ("###", 1583871098),
+            ("000", 2628203),
+            ("a9#", 214475550),
+        ] {
+            assert_eq!(Search::djb2tri(trigram), hash, "{}", trigram);
+        }
+    }
+
+    // no trigram of ascii characters hashes to -2^31, so the edge is checked on its own
+    #[test]
+    fn js_abs() {
+        assert_eq!(Search::js_abs(i32::MIN), 1 << 31);
+        assert_eq!(Search::js_abs(-5), 5);
+        assert_eq!(Search::js_abs(i32::MAX), i32::MAX as u64);
+    }
+
+    #[test]
+    fn bit_positions() {
+        let cases: [(u64, u64, u32, &[u64]); 12] = [
+            (1223377660, 64, 1, &[60]),
+            (1223377660, 64, 3, &[60, 37, 14]),
+            (1223377660, 1000, 2, &[660, 13]),
+            (1223377660, 8192, 3, &[764, 101, 7630]),
+            (1223377660, 1 << 20, 3, &[738044, 286821, 884174]),
+            (456590881, 64, 3, &[33, 2, 35]),
+            (456590881, 1000, 2, &[881, 826]),
+            (456590881, 1 << 20, 3, &[460321, 683266, 906211]),
+            // the biggest hash there is, Math.abs(-2^31)
+            (1 << 31, 64, 1, &[0]),
+            (1 << 31, 64, 3, &[0, 1, 2]),
+            (1 << 31, 1000, 2, &[648, 225]),
+            (1 << 31, 1 << 20, 3, &[0, 1, 2]),
+        ];
+        for (hash, bits, hashes, expected) in cases {
+            let positions: Vec<u64> = Search::bit_positions(hash, bits, hashes).collect();
+            assert_eq!(positions, expected, "{} {} {}", hash, bits, hashes);
+        }
+    }
+
+    #[test]
+    fn filters() {
+        assert_eq!(encoded_blossom("Hello, World!", 64, 3), "hkAG6GEEW5Q=");
+        assert_eq!(
+            encoded_blossom("Hello, World!", 128, 1),
+            "AAAAgAEAAgAAAAYAAAQQEA=="
+        );
+        assert_eq!(
+            encoded_blossom("Café Ünïcode: İstanbul", 256, 2),
+            "AAAAAwLYAABAQCQAACAQEAAAEAQEAIQAABhgQACAICA="
+        );
+    }
+}
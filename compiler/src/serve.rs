@@ -0,0 +1,294 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::{commands::Commands, config::Config, watch::Watcher};
+
+// browsers listen here for reloads, it's outside of any base path on purpose
+const RELOAD_PATH: &str = "/__blag/reload";
+
+// added to every html page served, reloads the page whenever the site is rebuilt
+const RELOAD_SCRIPT: &str =
+    r#"<script>new EventSource("/__blag/reload").onmessage = () => location.reload();</script>"#;
+
+// a tiny static file server for previewing the output directory, mounted under the base path
+// only meant for localhost, GET and HEAD only, one thread per connection
+// the site is rebuilt whenever an article or the tracking info changes, and open pages reload
+pub struct Server {}
+
+// everything a connection thread needs
+struct Site {
+    root: PathBuf,
+    base_path: String,
+    build_version: Arc<AtomicU64>, // bumped after every rebuild
+}
+
+impl Server {
+    pub fn run(config: &Config, port: u16) -> ExitCode {
+        // start from an up to date site, failures are printed but don't stop the server
+        let _ = Commands::build(config, false, false, false, false);
+
+        let build_version = Arc::new(AtomicU64::new(0));
+        Self::spawn_rebuilder(config.clone(), build_version.clone());
+
+        let address = format!("127.0.0.1:{}", port);
+        let listener = match TcpListener::bind(&address) {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("error: failed to listen on {}: {}", address, e);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        println!(
+            "serving {:?} at http://{}{}/ (ctrl-c to stop)",
+            config.output_dir, address, config.base_path
+        );
+
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(s) => s,
+                Err(e) => {
+                    eprintln!("warning: failed to accept connection: {}", e);
+                    continue;
+                }
+            };
+
+            let site = Site {
+                root: config.output_dir.clone(),
+                base_path: config.base_path.clone(),
+                build_version: build_version.clone(),
+            };
+            std::thread::spawn(move || {
+                if let Err(e) = Self::handle(stream, &site) {
+                    eprintln!("warning: failed to respond: {}", e);
+                }
+            });
+        }
+
+        ExitCode::SUCCESS
+    }
+
+    // watch the input directory and the info file, rebuild on every change
+    fn spawn_rebuilder(config: Config, build_version: Arc<AtomicU64>) {
+        std::thread::spawn(move || {
+            let mut watcher =
+                Watcher::new(vec![config.input_dir.clone(), config.info_file.clone()]);
+            loop {
+                let changed = watcher.wait_for_changes();
+                Commands::rebuild(&config, &changed);
+                // the build writes the info file, that isn't a change worth another build
+                watcher.refresh();
+                build_version.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+    }
+
+    fn handle(mut stream: TcpStream, site: &Site) -> std::io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        // drain the headers, we don't use any of them
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line == "\r\n" || line == "\n" {
+                break;
+            }
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or("");
+        let target = parts.next().unwrap_or("/");
+
+        if method != "GET" && method != "HEAD" {
+            return Self::respond(
+                &mut stream,
+                "405 Method Not Allowed",
+                "text/plain",
+                b"",
+                true,
+            );
+        }
+        let head_only = method == "HEAD";
+
+        if target == RELOAD_PATH {
+            return Self::stream_reloads(&mut stream, &site.build_version);
+        }
+
+        // the site only exists under the base path, like when it's deployed
+        let path = target.split(['?', '#']).next().unwrap_or("/");
+        if !site.base_path.is_empty() && (path == "/" || path == site.base_path) {
+            return Self::redirect(&mut stream, &format!("{}/", site.base_path));
+        }
+        let inner_target = match target.strip_prefix(&site.base_path) {
+            Some(rest) if rest.starts_with('/') => rest,
+            _ => {
+                println!("404 {}", target);
+                return Self::respond(
+                    &mut stream,
+                    "404 Not Found",
+                    "text/plain",
+                    b"not found",
+                    head_only,
+                );
+            }
+        };
+
+        let file_path = match Self::resolve(&site.root, inner_target) {
+            Some(p) => p,
+            None => {
+                return Self::respond(
+                    &mut stream,
+                    "404 Not Found",
+                    "text/plain",
+                    b"not found",
+                    head_only,
+                );
+            }
+        };
+
+        match std::fs::read(&file_path) {
+            Ok(mut body) => {
+                println!("200 {}", target);
+                if file_path.extension().is_some_and(|e| e == "html") {
+                    body = Self::inject_reload_script(body);
+                }
+                Self::respond(
+                    &mut stream,
+                    "200 OK",
+                    Self::content_type(&file_path),
+                    &body,
+                    head_only,
+                )
+            }
+            Err(_) => {
+                println!("404 {}", target);
+                Self::respond(
+                    &mut stream,
+                    "404 Not Found",
+                    "text/plain",
+                    b"not found",
+                    head_only,
+                )
+            }
+        }
+    }
+
+    // server-sent events: one message whenever the build version changes
+    fn stream_reloads(stream: &mut TcpStream, build_version: &AtomicU64) -> std::io::Result<()> {
+        let header = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\nConnection: keep-alive\r\n\r\nretry: 1000\n\n";
+        stream.write_all(header.as_bytes())?;
+        stream.flush()?;
+
+        let seen = build_version.load(Ordering::SeqCst);
+        loop {
+            std::thread::sleep(Duration::from_millis(250));
+            if build_version.load(Ordering::SeqCst) != seen {
+                stream.write_all(b"data: reload\n\n")?;
+                return stream.flush();
+            }
+            // a comment, which fails once the browser has gone away
+            stream.write_all(b": ping\n\n")?;
+        }
+    }
+
+    fn inject_reload_script(body: Vec<u8>) -> Vec<u8> {
+        let html = match String::from_utf8(body) {
+            Ok(h) => h,
+            Err(e) => return e.into_bytes(),
+        };
+        let html = match html.rfind("</body>") {
+            Some(i) => format!("{}{}{}", &html[..i], RELOAD_SCRIPT, &html[i..]),
+            None => html + RELOAD_SCRIPT,
+        };
+        html.into_bytes()
+    }
+
+    fn redirect(stream: &mut TcpStream, location: &str) -> std::io::Result<()> {
+        let header = format!(
+            "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            location
+        );
+        stream.write_all(header.as_bytes())?;
+        stream.flush()
+    }
+
+    // map a request target onto a file under root, refusing anything that escapes it
+    fn resolve(root: &Path, target: &str) -> Option<PathBuf> {
+        let path = target.split(['?', '#']).next().unwrap_or("/");
+        let path = Self::percent_decode(path)?;
+
+        let mut file_path = root.to_path_buf();
+        for component in Path::new(path.trim_start_matches('/')).components() {
+            match component {
+                Component::Normal(part) => file_path.push(part),
+                Component::CurDir => {}
+                _ => return None,
+            }
+        }
+
+        if file_path.is_dir() {
+            file_path.push("index.html");
+        }
+        Some(file_path)
+    }
+
+    fn percent_decode(text: &str) -> Option<String> {
+        let bytes = text.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' && i + 2 < bytes.len() {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            } else {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+        String::from_utf8(decoded).ok()
+    }
+
+    fn content_type(path: &Path) -> &'static str {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("html") => "text/html; charset=utf-8",
+            Some("css") => "text/css; charset=utf-8",
+            Some("js") => "text/javascript; charset=utf-8",
+            Some("json") => "application/json",
+            Some("xml") => "application/xml",
+            Some("txt") => "text/plain; charset=utf-8",
+            Some("svg") => "image/svg+xml",
+            Some("png") => "image/png",
+            Some("jpg") | Some("jpeg") => "image/jpeg",
+            Some("gif") => "image/gif",
+            Some("ico") => "image/x-icon",
+            _ => "application/octet-stream",
+        }
+    }
+
+    fn respond(
+        stream: &mut TcpStream,
+        status: &str,
+        content_type: &str,
+        body: &[u8],
+        head_only: bool,
+    ) -> std::io::Result<()> {
+        let header = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+            status,
+            content_type,
+            body.len()
+        );
+        stream.write_all(header.as_bytes())?;
+        if !head_only {
+            stream.write_all(body)?;
+        }
+        stream.flush()
+    }
+}
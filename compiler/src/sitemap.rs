@@ -0,0 +1,120 @@
+use serde::Deserialize;
+
+use crate::{
+    config::Config,
+    error::Result,
+    html::IndexHtml,
+    info::{ArticleInfo, InfoWrangler},
+    tags::TagPages,
+    utils::Utils,
+};
+
+// sitemap.xml lists every page for search engines, robots.txt points them at it
+// like the feeds, both need absolute urls, so they're only written when site_url is set
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SitemapOptions {
+    pub enabled: bool,
+    pub robots: bool,          // also write robots.txt
+    pub disallow: Vec<String>, // paths robots.txt asks crawlers to stay out of, like "/blag/drafts/"
+}
+
+impl Default for SitemapOptions {
+    fn default() -> Self {
+        SitemapOptions {
+            enabled: true,
+            robots: true,
+            disallow: vec![],
+        }
+    }
+}
+
+pub struct Sitemap {}
+
+impl Sitemap {
+    pub fn save(config: &Config, info_wrangler: &InfoWrangler) -> Result<()> {
+        if !config.sitemap.enabled {
+            return Ok(());
+        }
+        let Some(site_root) = config.site_root() else {
+            return Ok(());
+        };
+
+        Utils::write_file(
+            &config.output_dir.join("sitemap.xml"),
+            Self::sitemap_xml(&site_root, info_wrangler),
+        )?;
+        if config.sitemap.robots {
+            Utils::write_file(
+                &config.output_dir.join("robots.txt"),
+                Self::robots_txt(config, &site_root),
+            )?;
+        }
+        Ok(())
+    }
+
+    // (url relative to the site root, last modified)
+    fn pages(info_wrangler: &InfoWrangler) -> Vec<(String, u64)> {
+        let published = info_wrangler.get_published();
+        let last_modified = |article: &&ArticleInfo| article.updated_at.max(article.created_at);
+
+        let newest = published.iter().map(last_modified).max().unwrap_or(0);
+        let pages_of_index = (0..IndexHtml::page_count(info_wrangler))
+            .map(|page_num| (IndexHtml::page_url(page_num), newest));
+        let mut pages: Vec<(String, u64)> = pages_of_index.collect();
+        for article in &published {
+            pages.push((
+                format!("/{}.html", article.safe_filename),
+                last_modified(article),
+            ));
+        }
+
+        let tags = TagPages::tags(info_wrangler);
+        if !tags.is_empty() {
+            pages.push(("/tags/".to_string(), newest));
+        }
+        for tag in &tags {
+            let newest_tagged = tag.articles.iter().map(last_modified).max().unwrap_or(0);
+            pages.push((
+                format!("/tags/{}.html", TagPages::page_name(&tag.safe_name)),
+                newest_tagged,
+            ));
+        }
+        pages
+    }
+
+    fn sitemap_xml(site_root: &str, info_wrangler: &InfoWrangler) -> String {
+        let mut xml = String::from(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+"#,
+        );
+        for (page, last_modified) in Self::pages(info_wrangler) {
+            let lastmod = match Utils::format_rfc3339(last_modified) {
+                Some(date) if last_modified > 0 => format!("<lastmod>{}</lastmod>", date),
+                _ => String::new(),
+            };
+            xml.push_str(&format!(
+                "    <url><loc>{}</loc>{}</url>\n",
+                Utils::escape_html(&format!("{}{}", site_root, page)),
+                lastmod
+            ));
+        }
+        xml.push_str("</urlset>\n");
+        xml
+    }
+
+    // crawlers only read robots.txt at the root of a host,
+    // so with a base_path this needs copying there to have any effect
+    fn robots_txt(config: &Config, site_root: &str) -> String {
+        let mut robots = String::from("User-agent: *\n");
+        if config.sitemap.disallow.is_empty() {
+            robots.push_str("Disallow:\n");
+        }
+        for path in &config.sitemap.disallow {
+            robots.push_str(&format!("Disallow: {}\n", path));
+        }
+        robots.push_str(&format!("\nSitemap: {}/sitemap.xml\n", site_root));
+        robots
+    }
+}
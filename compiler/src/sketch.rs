@@ -0,0 +1,82 @@
+// MinHash sketches, used to notice when a "new" article is really an old one that was renamed
+// the sketch is stored in the info file, so the hashing here must never change
+pub struct Sketch {}
+
+const NUM_HASHES: usize = 64;
+const SHINGLE_WORDS: usize = 3;
+
+// two articles at least this similar are considered the same article
+pub const RENAME_SIMILARITY: f64 = 0.8;
+
+impl Sketch {
+    // returns a base64-encoded list of NUM_HASHES little-endian u32 minimums
+    // or an empty string for content with no words at all
+    pub fn compute(content: &str) -> String {
+        let words: Vec<String> = content
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_lowercase())
+            .collect();
+
+        if words.is_empty() {
+            return String::new();
+        }
+
+        // short articles still get one shingle
+        let shingles: Vec<String> = if words.len() < SHINGLE_WORDS {
+            vec![words.join(" ")]
+        } else {
+            words.windows(SHINGLE_WORDS).map(|w| w.join(" ")).collect()
+        };
+
+        let mut minimums = [u32::MAX; NUM_HASHES];
+        for shingle in &shingles {
+            for (seed, min) in minimums.iter_mut().enumerate() {
+                let hash = Self::fnv1a(shingle.as_bytes(), seed as u64);
+                if hash < *min {
+                    *min = hash;
+                }
+            }
+        }
+
+        let bytes: Vec<u8> = minimums.iter().flat_map(|m| m.to_le_bytes()).collect();
+        base64::Engine::encode(&base64::engine::general_purpose::STANDARD, bytes)
+    }
+
+    // estimated jaccard similarity of the two sketched documents, between 0 and 1
+    // None if either sketch is missing or malformed
+    pub fn similarity(a: &str, b: &str) -> Option<f64> {
+        let a = Self::decode(a)?;
+        let b = Self::decode(b)?;
+        let matching = a.iter().zip(b.iter()).filter(|(x, y)| x == y).count();
+        Some(matching as f64 / NUM_HASHES as f64)
+    }
+
+    fn decode(sketch: &str) -> Option<Vec<u32>> {
+        let bytes =
+            base64::Engine::decode(&base64::engine::general_purpose::STANDARD, sketch).ok()?;
+        if bytes.len() != NUM_HASHES * 4 {
+            return None;
+        }
+        Some(
+            bytes
+                .chunks_exact(4)
+                .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                .collect(),
+        )
+    }
+
+    // 64-bit FNV-1a, seeded by mixing the seed into the offset basis
+    // then a murmur3-style finalizer so different seeds give unrelated results
+    fn fnv1a(bytes: &[u8], seed: u64) -> u32 {
+        let mut hash: u64 = 0xcbf29ce484222325 ^ seed.wrapping_mul(0x9e3779b97f4a7c15);
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+        hash ^= hash >> 33;
+        (hash >> 32) as u32
+    }
+}
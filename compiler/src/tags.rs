@@ -0,0 +1,224 @@
+use std::path::Path;
+
+use crate::{
+    config::Config,
+    error::{Error, Result},
+    info::{ArticleInfo, InfoWrangler},
+    utils::Utils,
+};
+
+// tags/<tag>.html lists the articles with that tag, newest first
+// tags/index.html lists every tag, with how many articles have it
+pub struct TagPages {}
+
+// a tag called "index" can't have tags/index.html, safe names never have a dot so this one is free
+const INDEX_TAG_PAGE: &str = "index.tag";
+
+pub struct Tag<'a> {
+    pub name: String,      // as written in the first article that used it
+    pub safe_name: String, // url-safe, tags that only differ in case or punctuation are the same tag
+    pub articles: Vec<&'a ArticleInfo>,
+}
+
+impl TagPages {
+    pub fn save(config: &Config, info_wrangler: &mut InfoWrangler) -> Result<()> {
+        info_wrangler.sort_created_at();
+        let tags = Self::tags(info_wrangler);
+
+        let tags_dir = config.output_dir.join("tags");
+        let page_names: Vec<String> = tags
+            .iter()
+            .map(|tag| Self::page_name(&tag.safe_name))
+            .collect();
+        Self::remove_stale(&tags_dir, info_wrangler, &page_names)?;
+
+        for (tag, page_name) in tags.iter().zip(&page_names) {
+            let file_path = tags_dir.join(format!("{}.html", page_name));
+            Utils::write_file(&file_path, Self::tag_html(config, tag))?;
+        }
+        Utils::write_file(
+            &tags_dir.join("index.html"),
+            Self::index_html(config, &tags),
+        )
+    }
+
+    // tags can disappear, so delete the pages of tags that are gone
+    // articles can't be in a tags folder anymore (see InfoWrangler::upsert), but pages of ones
+    // tracked from before are left alone
+    fn remove_stale(
+        tags_dir: &Path,
+        info_wrangler: &InfoWrangler,
+        page_names: &[String],
+    ) -> Result<()> {
+        let entries = match std::fs::read_dir(tags_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(Error::io("read tag pages", tags_dir, e)),
+        };
+        for entry in entries {
+            let path = entry
+                .map_err(|e| Error::io("read tag pages", tags_dir, e))?
+                .path();
+            if !path.is_file() || path.extension().is_none_or(|ext| ext != "html") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            if name == "index"
+                || page_names.iter().any(|page_name| page_name == name)
+                || info_wrangler.is_output(&format!("tags/{}", name))
+            {
+                continue;
+            }
+            std::fs::remove_file(&path).map_err(|e| Error::io("delete tag page", &path, e))?;
+        }
+        Ok(())
+    }
+
+    // every tag of every published article, alphabetically, keeping the order of the articles
+    pub fn tags(info_wrangler: &InfoWrangler) -> Vec<Tag<'_>> {
+        let mut tags: Vec<Tag> = Vec::new();
+        for article in info_wrangler.get_published() {
+            for name in &article.tags {
+                let Some(safe_name) = Self::safe_tag(name) else {
+                    continue;
+                };
+                match tags.iter_mut().find(|tag| tag.safe_name == safe_name) {
+                    Some(tag) => {
+                        // an article could list the same tag twice
+                        if !tag
+                            .articles
+                            .iter()
+                            .any(|a| a.safe_filename == article.safe_filename)
+                        {
+                            tag.articles.push(article);
+                        }
+                    }
+                    None => tags.push(Tag {
+                        name: name.clone(),
+                        safe_name,
+                        articles: vec![article],
+                    }),
+                }
+            }
+        }
+
+        tags.sort_by(|a, b| a.safe_name.cmp(&b.safe_name));
+        tags
+    }
+
+    // the url-safe name of a tag, None if nothing url-safe is left of it
+    pub fn safe_tag(tag: &str) -> Option<String> {
+        Utils::format_safe_filename(tag.trim()).filter(|name| !name.is_empty())
+    }
+
+    // the file name of a tag's page, without the extension
+    pub fn page_name(safe_name: &str) -> String {
+        match safe_name {
+            "index" => INDEX_TAG_PAGE.to_string(),
+            _ => safe_name.to_string(),
+        }
+    }
+
+    // url of a tag's page, with the base path
+    pub fn tag_url(config: &Config, tag: &str) -> Option<String> {
+        Self::safe_tag(tag)
+            .map(|name| format!("{}/tags/{}.html", config.base_path, Self::page_name(&name)))
+    }
+
+    fn tag_html(config: &Config, tag: &Tag) -> String {
+        let rows: Vec<String> = tag
+            .articles
+            .iter()
+            .map(|article| {
+                format!(
+                    r#"<span class="text-right">{}</span><a href="{}/{}.html">{}</a>"#,
+                    Utils::format_iso_date(article.created_at).unwrap_or_default(),
+                    Utils::escape_html(&config.base_path),
+                    article.safe_filename,
+                    Utils::escape_html(article.title())
+                )
+            })
+            .collect();
+
+        Self::page(
+            config,
+            &format!("tagged: {}", tag.name),
+            &format!(
+                r#"<p><a href="{}/tags/">all tags</a></p>
+        <div class="listing">
+            {}
+        </div>"#,
+                Utils::escape_html(&config.base_path),
+                rows.join("\n            ")
+            ),
+        )
+    }
+
+    fn index_html(config: &Config, tags: &[Tag]) -> String {
+        let rows: Vec<String> = tags
+            .iter()
+            .map(|tag| {
+                format!(
+                    r#"<span class="text-right">{}</span><a href="{}/tags/{}.html">{}</a>"#,
+                    tag.articles.len(),
+                    Utils::escape_html(&config.base_path),
+                    Self::page_name(&tag.safe_name),
+                    Utils::escape_html(&tag.name)
+                )
+            })
+            .collect();
+
+        Self::page(
+            config,
+            "tags",
+            &format!(
+                r#"<div class="listing">
+            {}
+        </div>"#,
+                rows.join("\n            ")
+            ),
+        )
+    }
+
+    fn page(config: &Config, heading: &str, body: &str) -> String {
+        let heading = Utils::escape_html(heading);
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <link rel="icon" href="data:,">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <meta charset="UTF-8">
+    <title>{} - {}</title>
+    <style>
+        .text-right {{
+            text-align: right;
+        }}
+        .listing {{
+            display: grid;
+            grid-template-columns: max-content auto;
+            column-gap: 0.6rem;
+            row-gap: 0.4rem;
+        }}
+    </style>
+</head>
+<body>
+    <div style="max-width:800px;margin-left:auto;margin-right:auto;">
+        <p><a href="{}/">{}</a></p>
+        <h1>{}</h1>
+        {}
+    </div>
+</body>
+</html>
+"#,
+            heading,
+            Utils::escape_html(&config.title),
+            Utils::escape_html(&config.base_path),
+            Utils::escape_html(&config.title),
+            heading,
+            body
+        )
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::tags::TagPages;
+
+// words too common to say anything about an article
+const STOPWORDS: &[&str] = &[
+    "about",
+    "above",
+    "after",
+    "again",
+    "against",
+    "all",
+    "also",
+    "am",
+    "an",
+    "and",
+    "any",
+    "are",
+    "aren't",
+    "as",
+    "at",
+    "be",
+    "because",
+    "been",
+    "before",
+    "being",
+    "below",
+    "between",
+    "both",
+    "but",
+    "by",
+    "can",
+    "could",
+    "did",
+    "do",
+    "does",
+    "doing",
+    "don't",
+    "down",
+    "during",
+    "each",
+    "even",
+    "every",
+    "few",
+    "for",
+    "from",
+    "further",
+    "get",
+    "gets",
+    "got",
+    "had",
+    "has",
+    "have",
+    "having",
+    "he",
+    "her",
+    "here",
+    "hers",
+    "herself",
+    "him",
+    "himself",
+    "his",
+    "how",
+    "however",
+    "i",
+    "if",
+    "in",
+    "into",
+    "is",
+    "isn't",
+    "it",
+    "it's",
+    "its",
+    "itself",
+    "just",
+    "like",
+    "make",
+    "makes",
+    "many",
+    "may",
+    "me",
+    "might",
+    "more",
+    "most",
+    "much",
+    "must",
+    "my",
+    "myself",
+    "need",
+    "needs",
+    "no",
+    "nor",
+    "not",
+    "now",
+    "of",
+    "off",
+    "on",
+    "once",
+    "one",
+    "only",
+    "or",
+    "other",
+    "our",
+    "ours",
+    "ourselves",
+    "out",
+    "over",
+    "own",
+    "really",
+    "same",
+    "she",
+    "should",
+    "so",
+    "some",
+    "still",
+    "such",
+    "than",
+    "that",
+    "that's",
+    "the",
+    "their",
+    "theirs",
+    "them",
+    "themselves",
+    "then",
+    "there",
+    "these",
+    "they",
+    "thing",
+    "things",
+    "this",
+    "those",
+    "through",
+    "to",
+    "too",
+    "two",
+    "under",
+    "until",
+    "up",
+    "use",
+    "used",
+    "using",
+    "very",
+    "was",
+    "way",
+    "we",
+    "well",
+    "were",
+    "what",
+    "when",
+    "where",
+    "which",
+    "while",
+    "who",
+    "whom",
+    "why",
+    "will",
+    "with",
+    "would",
+    "you",
+    "your",
+    "yours",
+    "yourself",
+    "yourselves",
+];
+
+// words shorter than this are mostly noise
+const MIN_WORD_LENGTH: usize = 3;
+
+// suggests tags for articles: the words that are common in an article, but rare in the others
+pub struct TfIdf {}
+
+pub struct Document<'a> {
+    pub text: &'a str,               // the article's plain text
+    pub existing_tags: &'a [String], // never suggested again
+}
+
+impl TfIdf {
+    // the top `count` words of each document, best first
+    // tf is how often the word is in the document, relative to its length
+    // idf is smoothed (ln((1 + n) / (1 + df)) + 1), so a blog of one article still gets suggestions
+    pub fn suggest(documents: &[Document], count: usize) -> Vec<Vec<String>> {
+        let term_counts: Vec<HashMap<String, usize>> = documents
+            .iter()
+            .map(|document| Self::term_counts(document.text))
+            .collect();
+
+        let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+        for counts in &term_counts {
+            for term in counts.keys() {
+                *document_frequency.entry(term).or_default() += 1;
+            }
+        }
+
+        let n = documents.len() as f64;
+        documents
+            .iter()
+            .zip(&term_counts)
+            .map(|(document, counts)| {
+                let existing: HashSet<String> = document
+                    .existing_tags
+                    .iter()
+                    .filter_map(|tag| TagPages::safe_tag(tag))
+                    .collect();
+                let total = counts.values().sum::<usize>().max(1) as f64;
+
+                let mut scored: Vec<(&String, f64)> = counts
+                    .iter()
+                    .filter(|(term, _)| {
+                        TagPages::safe_tag(term).is_none_or(|safe| !existing.contains(&safe))
+                    })
+                    .map(|(term, term_count)| {
+                        let df = document_frequency.get(term.as_str()).copied().unwrap_or(0) as f64;
+                        let idf = ((1.0 + n) / (1.0 + df)).ln() + 1.0;
+                        (term, *term_count as f64 / total * idf)
+                    })
+                    .collect();
+                // ties go alphabetically, so the suggestions don't change from run to run
+                scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+                scored
+                    .into_iter()
+                    .take(count)
+                    .map(|(term, _)| term.clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    // lowercase words, without stopwords, short words or plain numbers
+    // apostrophes stay inside words, so "don't" is one (stop)word, even after smart punctuation
+    fn term_counts(text: &str) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        let text = text.replace('\u{2019}', "'");
+        let words = text
+            .split(|c: char| !(c.is_alphanumeric() || c == '\''))
+            .map(|word| word.trim_matches('\'').to_lowercase());
+        for word in words {
+            if word.chars().count() < MIN_WORD_LENGTH
+                || word.chars().all(|c| c.is_numeric())
+                || STOPWORDS.contains(&word.as_str())
+            {
+                continue;
+            }
+            *counts.entry(word).or_default() += 1;
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggest(texts: &[(&str, &[String])], count: usize) -> Vec<Vec<String>> {
+        let documents: Vec<Document> = texts
+            .iter()
+            .map(|(text, existing_tags)| Document {
+                text,
+                existing_tags,
+            })
+            .collect();
+        TfIdf::suggest(&documents, count)
+    }
+
+    #[test]
+    fn rare_words_rank_first() {
+        let suggestions = suggest(
+            &[
+                ("rust rust rust borrow checker garden", &[]),
+                ("tomatoes tomatoes garden soil", &[]),
+            ],
+            3,
+        );
+        // garden is in both, so it loses to the words only one has, and ties go alphabetically
+        assert_eq!(suggestions[0], ["rust", "borrow", "checker"]);
+        assert_eq!(suggestions[1], ["tomatoes", "soil", "garden"]);
+    }
+
+    #[test]
+    fn stopwords_short_words_and_numbers_are_skipped() {
+        let suggestions = suggest(
+            &[("the and of it's ox 2024 42 don\u{2019}t lemons", &[])],
+            5,
+        );
+        assert_eq!(suggestions, [["lemons"]]);
+    }
+
+    #[test]
+    fn existing_tags_are_not_suggested() {
+        let existing = ["Rust".to_string(), "borrow-checker".to_string()];
+        let suggestions = suggest(&[("rust rust borrow checker lifetimes", &existing)], 5);
+        // "Rust" is the same tag as "rust", "borrow-checker" is neither word
+        assert_eq!(suggestions, [["borrow", "checker", "lifetimes"]]);
+    }
+
+    #[test]
+    fn empty_corpus() {
+        assert!(suggest(&[], 5).is_empty());
+        assert_eq!(
+            suggest(&[("", &[]), ("the of and", &[])], 5),
+            vec![Vec::<String>::new(); 2]
+        );
+        assert_eq!(suggest(&[("lemons", &[])], 0), vec![Vec::<String>::new()]);
+    }
+}
@@ -1,5 +1,7 @@
 use std::path::Path;
 
+use crate::error::{Error, Result};
+
 pub struct Utils {}
 
 const MAX_SAFE_FILENAME_LENGTH: usize = 256;
@@ -49,4 +51,68 @@ impl Utils {
         // Month day, Year
         Some(datetime.format("%B %e, %Y").to_string())
     }
-}
\ No newline at end of file
+
+    // like 2026-01-05, in UTC like the index page
+    pub fn format_iso_date(timestamp: u64) -> Option<String> {
+        let datetime = chrono::DateTime::from_timestamp(i64::try_from(timestamp).ok()?, 0)?;
+        Some(datetime.format("%Y-%m-%d").to_string())
+    }
+
+    // like 2026-01-05T00:00:00+00:00, for atom and sitemaps
+    pub fn format_rfc3339(timestamp: u64) -> Option<String> {
+        let datetime = chrono::DateTime::from_timestamp(i64::try_from(timestamp).ok()?, 0)?;
+        Some(datetime.to_rfc3339())
+    }
+
+    // like Mon, 05 Jan 2026 00:00:00 +0000, for rss
+    pub fn format_rfc2822(timestamp: u64) -> Option<String> {
+        let datetime = chrono::DateTime::from_timestamp(i64::try_from(timestamp).ok()?, 0)?;
+        Some(datetime.to_rfc2822())
+    }
+
+    // base64-encoded sha256
+    pub fn hash_base64(bytes: &[u8]) -> String {
+        let digest = <sha2::Sha256 as sha2::Digest>::digest(bytes);
+        base64::Engine::encode(&base64::engine::general_purpose::STANDARD, digest)
+    }
+
+    // write a file, creating its parent directories first
+    pub fn write_file(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| Error::io("create output directory", parent, e))?;
+        }
+        std::fs::write(path, contents).map_err(|e| Error::io("write file", path, e))
+    }
+
+    // "YYYY-MM-DD" (midnight UTC) or a full RFC 3339 timestamp, to unix seconds
+    pub fn parse_date(text: &str) -> Option<u64> {
+        let text = text.trim();
+        let timestamp = match chrono::DateTime::parse_from_rfc3339(text) {
+            Ok(dt) => dt.timestamp(),
+            Err(_) => chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)?
+                .and_utc()
+                .timestamp(),
+        };
+        u64::try_from(timestamp).ok()
+    }
+
+    // minimal escaping for text placed inside html elements or attributes
+    pub fn escape_html(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&#39;")
+    }
+
+    // json that goes inside an inline <script>, where "</script>" in a string would end the script
+    // the escapes are still the same json
+    pub fn escape_script_json(json: &str) -> String {
+        json.replace('<', "\\u003c")
+            .replace('>', "\\u003e")
+            .replace('&', "\\u0026")
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// how often files are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+// changes are only reported once nothing else has changed for this long,
+// so saving several files at once (or an editor's write-then-rename) is a single change
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+// modification time and size, either changing counts as a change
+type FileStamp = (Option<SystemTime>, u64);
+
+// notices files being added, removed or modified under a set of files and directories
+// polls modification times instead of using os-specific notifications, which is plenty for a blog
+pub struct Watcher {
+    roots: Vec<PathBuf>,
+    snapshot: HashMap<PathBuf, FileStamp>,
+}
+
+impl Watcher {
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        let mut watcher = Watcher {
+            roots,
+            snapshot: HashMap::new(),
+        };
+        watcher.refresh();
+        watcher
+    }
+
+    // forget about any changes so far, e.g. after the build wrote to a watched file itself
+    pub fn refresh(&mut self) {
+        self.snapshot = self.scan();
+    }
+
+    // blocks until something changes, then returns every changed path (sorted)
+    pub fn wait_for_changes(&mut self) -> Vec<PathBuf> {
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let mut changed = self.changes();
+            if changed.is_empty() {
+                continue;
+            }
+
+            // keep collecting until things settle down
+            loop {
+                std::thread::sleep(DEBOUNCE);
+                let more = self.changes();
+                if more.is_empty() {
+                    break;
+                }
+                changed.extend(more);
+            }
+
+            changed.sort();
+            changed.dedup();
+            return changed;
+        }
+    }
+
+    fn changes(&mut self) -> Vec<PathBuf> {
+        let current = self.scan();
+        let mut changed: Vec<PathBuf> = current
+            .iter()
+            .filter(|(path, modified)| self.snapshot.get(*path) != Some(*modified))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.extend(
+            self.snapshot
+                .keys()
+                .filter(|path| !current.contains_key(*path))
+                .cloned(),
+        );
+        self.snapshot = current;
+        changed
+    }
+
+    fn scan(&self) -> HashMap<PathBuf, FileStamp> {
+        let mut files = HashMap::new();
+        for root in &self.roots {
+            Self::scan_path(root, &mut files);
+        }
+        files
+    }
+
+    // unreadable or missing paths are simply not there, they'll show up once they can be read
+    fn scan_path(path: &Path, files: &mut HashMap<PathBuf, FileStamp>) {
+        let metadata = match std::fs::metadata(path) {
+            Ok(m) => m,
+            Err(_) => return,
+        };
+
+        if !metadata.is_dir() {
+            files.insert(
+                path.to_path_buf(),
+                (metadata.modified().ok(), metadata.len()),
+            );
+            return;
+        }
+
+        if let Ok(read_dir) = std::fs::read_dir(path) {
+            for entry in read_dir.flatten() {
+                Self::scan_path(&entry.path(), files);
+            }
+        }
+    }
+}